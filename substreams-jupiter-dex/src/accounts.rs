@@ -0,0 +1,103 @@
+use substreams_solana::pb::sf::solana::r#type::v1::ConfirmedTransaction;
+use substreams_solana::Address;
+
+use crate::balances::{BalanceChanges, TokenAccountChange};
+
+// Account keys of a transaction in runtime order, as listed by
+// `ConfirmedTransaction::resolved_accounts`: the static message keys, then
+// the writable and the readonly addresses loaded from lookup tables.
+// Instruction account indexes and token balance account indexes point into
+// this list, not into `message.account_keys`.
+#[derive(Debug, Default)]
+pub struct ResolvedAccounts<'a> {
+    keys: Vec<&'a Vec<u8>>,
+}
+
+impl<'a> ResolvedAccounts<'a> {
+    // Empty for transactions without a message or meta, which
+    // `resolved_accounts` unwraps
+    pub fn from_transaction(tx: &'a ConfirmedTransaction) -> Self {
+        let has_message = tx.transaction.as_ref().and_then(|t| t.message.as_ref()).is_some();
+        if !has_message || tx.meta.is_none() {
+            return ResolvedAccounts::default();
+        }
+
+        ResolvedAccounts { keys: tx.resolved_accounts() }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&'a [u8]> {
+        self.keys.get(index).map(|key| key.as_slice())
+    }
+
+    // Fee payer, always the first static key
+    pub fn signer(&self) -> &'a [u8] {
+        self.get(0).unwrap_or(&[])
+    }
+
+    pub fn position(&self, key: &[u8]) -> Option<usize> {
+        self.keys.iter().position(|account| account.as_slice() == key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        self.keys.iter().map(|key| key.as_slice())
+    }
+}
+
+// Accounts of a single instruction, with the token balance changes of the
+// transaction they refer to
+pub struct InstructionAccounts<'a> {
+    pub accounts: Vec<Address<'a>>,
+    pub resolved: &'a ResolvedAccounts<'a>,
+    pub balances: &'a BalanceChanges,
+}
+
+impl<'a> InstructionAccounts<'a> {
+    // Base58 pubkey of the account at the given position of the instruction
+    pub fn key(&self, position: usize) -> Option<String> {
+        self.accounts.get(position).map(|account| account.to_string())
+    }
+
+    // Balance change of the token account at the given position, None for
+    // accounts without token balances
+    pub fn balance(&self, position: usize) -> Option<&'a TokenAccountChange> {
+        let account = self.accounts.get(position)?;
+        let index = self.resolved.position(account.0)?;
+        self.balances.get(index as u32)
+    }
+
+    // Mint of the token account at the given position
+    pub fn mint(&self, position: usize) -> Option<String> {
+        self.balance(position).map(|change| change.mint.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use substreams_solana::pb::sf::solana::r#type::v1::{Message, Transaction, TransactionStatusMeta};
+
+    use super::*;
+
+    #[test]
+    fn lists_the_lookup_table_addresses_after_the_static_keys() {
+        let mut tx = ConfirmedTransaction {
+            transaction: Some(Transaction {
+                message: Some(Message { account_keys: vec![vec![0; 32], vec![1; 32]], ..Default::default() }),
+                ..Default::default()
+            }),
+            meta: Some(TransactionStatusMeta {
+                loaded_writable_addresses: vec![vec![2; 32]],
+                loaded_readonly_addresses: vec![vec![3; 32]],
+                ..Default::default()
+            }),
+        };
+
+        let accounts = ResolvedAccounts::from_transaction(&tx);
+        assert_eq!(accounts.signer(), [0; 32].as_slice());
+        assert_eq!(accounts.position(&[3; 32]), Some(3));
+        assert_eq!(accounts.get(2), Some([2; 32].as_slice()));
+        assert_eq!(accounts.iter().count(), 4);
+
+        tx.meta = None;
+        assert_eq!(ResolvedAccounts::from_transaction(&tx).iter().count(), 0);
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use substreams::scalar::{BigDecimal, BigInt};
+use substreams_solana::pb::sf::solana::r#type::v1::{TokenBalance, TransactionStatusMeta};
+
+// Mint used to represent native SOL balance changes, the wSOL mint
+pub const NATIVE_MINT: &str = "So11111111111111111111111111111111111111112";
+const NATIVE_DECIMALS: u32 = 9;
+
+// Balance change of a single token account within a transaction, in native units
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccountChange {
+    pub account_index: u32,
+    pub mint: String,
+    pub owner: String,
+    pub pre_amount: u64,
+    pub post_amount: u64,
+    pub decimals: u32,
+    // Lamport balance of a system account rather than a token account
+    pub is_native: bool,
+}
+
+impl TokenAccountChange {
+    pub fn is_spent(&self) -> bool {
+        self.pre_amount > self.post_amount
+    }
+
+    pub fn is_received(&self) -> bool {
+        self.post_amount > self.pre_amount
+    }
+
+    // Absolute change of the balance
+    pub fn delta(&self) -> u64 {
+        self.pre_amount.abs_diff(self.post_amount)
+    }
+
+    pub fn normalized_delta(&self) -> BigDecimal {
+        BigInt::from(self.delta()).to_decimal(self.decimals as u64)
+    }
+}
+
+// Per-account token balance deltas of a transaction, keyed by the account
+// index of the token account. Accounts created during the transaction only
+// appear in the post balances and start from 0, accounts closed during the
+// transaction (e.g. temporary wSOL accounts) only appear in the pre balances
+// and end at 0.
+#[derive(Debug, Default)]
+pub struct BalanceChanges {
+    changes: BTreeMap<u32, TokenAccountChange>,
+}
+
+impl BalanceChanges {
+    pub fn from_meta(meta: &TransactionStatusMeta) -> Self {
+        let mut changes: BTreeMap<u32, TokenAccountChange> = BTreeMap::new();
+
+        for pre_balance in meta.pre_token_balances.iter() {
+            // Skip entries with empty mint addresses
+            if pre_balance.mint.is_empty() {
+                continue;
+            }
+
+            changes.insert(pre_balance.account_index, TokenAccountChange {
+                account_index: pre_balance.account_index,
+                mint: pre_balance.mint.clone(),
+                owner: pre_balance.owner.clone(),
+                pre_amount: raw_token_amount(pre_balance),
+                post_amount: 0,
+                decimals: token_decimals(pre_balance),
+                is_native: false,
+            });
+        }
+
+        for post_balance in meta.post_token_balances.iter() {
+            if post_balance.mint.is_empty() {
+                continue;
+            }
+
+            let change = changes.entry(post_balance.account_index).or_insert_with(|| TokenAccountChange {
+                account_index: post_balance.account_index,
+                mint: post_balance.mint.clone(),
+                owner: post_balance.owner.clone(),
+                pre_amount: 0,
+                post_amount: 0,
+                decimals: token_decimals(post_balance),
+                is_native: false,
+            });
+            change.post_amount = raw_token_amount(post_balance);
+        }
+
+        BalanceChanges { changes }
+    }
+
+    // Folds the lamport change of a system account into the table as native
+    // SOL. Swaps in and out of SOL wrap and unwrap wSOL in temporary token
+    // accounts that never show up in the token balances, so this is the only
+    // trace of the SOL leg. The transaction fee is excluded for the fee payer.
+    pub fn add_native_balance(&mut self, meta: &TransactionStatusMeta, account_index: u32, owner: &str) {
+        let index = account_index as usize;
+        let (Some(&pre_amount), Some(&post_amount)) = (meta.pre_balances.get(index), meta.post_balances.get(index)) else {
+            return;
+        };
+
+        let fee = if account_index == 0 { meta.fee } else { 0 };
+        self.changes.insert(account_index, TokenAccountChange {
+            account_index,
+            mint: NATIVE_MINT.to_string(),
+            owner: owner.to_string(),
+            pre_amount,
+            post_amount: post_amount.saturating_add(fee),
+            decimals: NATIVE_DECIMALS,
+            is_native: true,
+        });
+    }
+
+    pub fn get(&self, account_index: u32) -> Option<&TokenAccountChange> {
+        self.changes.get(&account_index)
+    }
+
+    // All token accounts of the transaction, in account index order
+    pub fn iter(&self) -> impl Iterator<Item = &TokenAccountChange> {
+        self.changes.values()
+    }
+
+    pub fn spent(&self) -> impl Iterator<Item = &TokenAccountChange> {
+        self.iter().filter(|c| c.is_spent())
+    }
+
+    pub fn received(&self) -> impl Iterator<Item = &TokenAccountChange> {
+        self.iter().filter(|c| c.is_received())
+    }
+
+    // Token accounts owned by the given base58 address
+    pub fn by_owner<'a>(&'a self, owner: &'a str) -> impl Iterator<Item = &'a TokenAccountChange> + 'a {
+        self.iter().filter(move |c| c.owner == owner)
+    }
+
+    // First token the owner spent. Native SOL is only used when none of the
+    // owner's token accounts decreased, as lamports also pay for account rent.
+    pub fn spent_by(&self, owner: &str) -> Option<&TokenAccountChange> {
+        self.iter().find(|c| c.owner == owner && c.is_spent() && !c.is_native)
+            .or_else(|| self.iter().find(|c| c.owner == owner && c.is_spent()))
+    }
+
+    // First token the owner received, native SOL last like in `spent_by`
+    pub fn received_by(&self, owner: &str) -> Option<&TokenAccountChange> {
+        self.iter().find(|c| c.owner == owner && c.is_received() && !c.is_native)
+            .or_else(|| self.iter().find(|c| c.owner == owner && c.is_received()))
+    }
+}
+
+// Raw native-unit amount of a token balance, taken from the exact u64 string
+// rather than the lossy ui_amount
+pub fn raw_token_amount(balance: &TokenBalance) -> u64 {
+    balance.ui_token_amount.as_ref()
+        .and_then(|a| a.amount.parse::<u64>().ok())
+        .unwrap_or(0)
+}
+
+fn token_decimals(balance: &TokenBalance) -> u32 {
+    balance.ui_token_amount.as_ref().map_or(0, |a| a.decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use substreams_solana::pb::sf::solana::r#type::v1::UiTokenAmount;
+
+    use super::*;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const BONK: &str = "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263";
+
+    fn token_balance(account_index: u32, mint: &str, owner: &str, amount: u64) -> TokenBalance {
+        TokenBalance {
+            account_index,
+            mint: mint.to_string(),
+            owner: owner.to_string(),
+            ui_token_amount: Some(UiTokenAmount { amount: amount.to_string(), decimals: 6, ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    // The user swaps USDC in account 1 for BONK received in account 3, created
+    // by the transaction. Account 2 is a pool vault closed by the transaction.
+    fn swap_meta() -> TransactionStatusMeta {
+        TransactionStatusMeta {
+            pre_token_balances: vec![
+                token_balance(1, USDC, "user", 5_000),
+                token_balance(2, USDC, "pool", 700),
+                token_balance(4, "", "user", 1),
+            ],
+            post_token_balances: vec![
+                token_balance(1, USDC, "user", 4_000),
+                token_balance(3, BONK, "user", 250),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn keys_balance_changes_by_account_index() {
+        let changes = BalanceChanges::from_meta(&swap_meta());
+        let accounts: Vec<(u32, u64, u64)> = changes.iter()
+            .map(|change| (change.account_index, change.pre_amount, change.post_amount))
+            .collect();
+        assert_eq!(accounts, vec![(1, 5_000, 4_000), (2, 700, 0), (3, 0, 250)]);
+        assert_eq!(changes.get(1).unwrap().delta(), 1_000);
+        assert_eq!(changes.get(3).unwrap().normalized_delta().to_string(), "0.000250");
+        assert_eq!(changes.get(4), None);
+    }
+
+    #[test]
+    fn finds_the_tokens_spent_and_received_by_an_owner() {
+        let changes = BalanceChanges::from_meta(&swap_meta());
+        assert_eq!(changes.spent_by("user").map(|c| c.mint.as_str()), Some(USDC));
+        assert_eq!(changes.received_by("user").map(|c| c.mint.as_str()), Some(BONK));
+        assert_eq!(changes.spent_by("pool").map(|c| c.account_index), Some(2));
+        assert_eq!(changes.received_by("pool"), None);
+        assert_eq!(changes.by_owner("user").count(), 2);
+    }
+
+    #[test]
+    fn adds_the_fee_back_to_the_fee_payer_native_balance() {
+        let meta = TransactionStatusMeta {
+            fee: 5_000,
+            pre_balances: vec![1_000_000, 2_000_000],
+            post_balances: vec![995_000, 1_500_000],
+            ..Default::default()
+        };
+        let mut changes = BalanceChanges::default();
+        changes.add_native_balance(&meta, 0, "signer");
+        changes.add_native_balance(&meta, 1, "trader");
+        changes.add_native_balance(&meta, 2, "missing");
+
+        // The signer only paid the fee, the trader spent 0.5 SOL
+        let signer = changes.get(0).unwrap();
+        assert!(signer.is_native && signer.mint == NATIVE_MINT);
+        assert_eq!(signer.delta(), 0);
+        assert_eq!(changes.get(1).unwrap().delta(), 500_000);
+        assert_eq!(changes.get(2), None);
+    }
+
+    #[test]
+    fn prefers_token_accounts_over_native_sol() {
+        // The user's system account is account 5
+        let meta = TransactionStatusMeta {
+            pre_balances: vec![0, 0, 0, 0, 0, 3_000_000],
+            post_balances: vec![0, 0, 0, 0, 0, 1_000_000],
+            ..swap_meta()
+        };
+        let mut changes = BalanceChanges::from_meta(&meta);
+        changes.add_native_balance(&meta, 5, "user");
+        assert_eq!(changes.spent_by("user").map(|c| c.mint.as_str()), Some(USDC));
+
+        // SOL is the spent side when no token account of the owner decreased
+        let mut changes = BalanceChanges::from_meta(&TransactionStatusMeta { pre_token_balances: vec![], ..meta.clone() });
+        changes.add_native_balance(&meta, 5, "user");
+        assert_eq!(changes.spent_by("user").map(|c| c.mint.as_str()), Some(NATIVE_MINT));
+    }
+}
@@ -0,0 +1,371 @@
+use borsh::BorshDeserialize;
+
+use crate::accounts::InstructionAccounts;
+use crate::events::event_args;
+use crate::pb::sf::jupiter::v1::{liquidity_change::Kind, LiquidityChange};
+
+// Anchor instruction discriminators for the Jupiter DCA program,
+// computed as sha256("global:<snake_case_name>")[..8]
+pub const OPEN_DCA: [u8; 8] = [36, 65, 185, 54, 1, 210, 100, 163];
+pub const OPEN_DCA_V2: [u8; 8] = [142, 119, 43, 109, 162, 52, 11, 177];
+pub const DEPOSIT: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+pub const WITHDRAW: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+pub const CLOSE_DCA: [u8; 8] = [22, 7, 33, 98, 168, 183, 34, 243];
+pub const INITIATE_FLASH_FILL: [u8; 8] = [143, 205, 3, 191, 162, 215, 245, 49];
+pub const FULFILL_FLASH_FILL: [u8; 8] = [115, 64, 226, 78, 33, 211, 105, 162];
+
+// Anchor event discriminators, sha256("event:<Name>")[..8]
+pub const OPENED_EVENT: [u8; 8] = [166, 172, 97, 9, 77, 76, 189, 109];
+pub const FILLED_EVENT: [u8; 8] = [134, 4, 17, 63, 221, 45, 177, 173];
+pub const CLOSED_EVENT: [u8; 8] = [50, 31, 87, 155, 135, 220, 195, 239];
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct OpenDcaParams {
+    pub application_idx: u64,
+    pub in_amount: u64,
+    pub in_amount_per_cycle: u64,
+    pub cycle_frequency: i64,
+    pub min_price: Option<u64>,
+    pub max_price: Option<u64>,
+    pub start_at: Option<i64>,
+    pub close_wsol_in_ata: Option<bool>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct OpenDcaV2Params {
+    pub application_idx: u64,
+    pub in_amount: u64,
+    pub in_amount_per_cycle: u64,
+    pub cycle_frequency: i64,
+    pub min_out_amount: Option<u64>,
+    pub max_out_amount: Option<u64>,
+    pub start_at: Option<i64>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Withdrawal {
+    In,
+    Out,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct WithdrawParams {
+    pub withdraw_amount: u64,
+    pub withdrawal: Withdrawal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DcaInstruction {
+    OpenDca(OpenDcaParams),
+    OpenDcaV2(OpenDcaV2Params),
+    Deposit { deposit_in: u64 },
+    Withdraw(WithdrawParams),
+    CloseDca,
+    // Lends the input amount of a cycle to the keeper, which swaps it and
+    // repays the output with fulfillFlashFill in the same transaction
+    InitiateFlashFill,
+    FulfillFlashFill { repay_amount: u64 },
+}
+
+impl DcaInstruction {
+    // Decodes a Jupiter DCA instruction, returns None for unknown
+    // discriminators or malformed args
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let (discriminator, mut args) = data.split_at(8);
+        match discriminator {
+            d if d == OPEN_DCA => OpenDcaParams::deserialize(&mut args).ok().map(Self::OpenDca),
+            d if d == OPEN_DCA_V2 => OpenDcaV2Params::deserialize(&mut args).ok().map(Self::OpenDcaV2),
+            d if d == DEPOSIT => u64::deserialize(&mut args).ok().map(|deposit_in| Self::Deposit { deposit_in }),
+            d if d == WITHDRAW => WithdrawParams::deserialize(&mut args).ok().map(Self::Withdraw),
+            d if d == CLOSE_DCA => Some(Self::CloseDca),
+            d if d == INITIATE_FLASH_FILL => Some(Self::InitiateFlashFill),
+            d if d == FULFILL_FLASH_FILL => u64::deserialize(&mut args).ok()
+                .map(|repay_amount| Self::FulfillFlashFill { repay_amount }),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::OpenDca(_) => "openDca",
+            Self::OpenDcaV2(_) => "openDcaV2",
+            Self::Deposit { .. } => "deposit",
+            Self::Withdraw(_) => "withdraw",
+            Self::CloseDca => "closeDca",
+            Self::InitiateFlashFill => "initiateFlashFill",
+            Self::FulfillFlashFill { .. } => "fulfillFlashFill",
+        }
+    }
+
+    // Tokens moved between the user and the DCA vault, in the order input
+    // mint, output mint. DCA positions have no receipt token, so the DCA
+    // account stands in as both the pool and the output token.
+    pub fn liquidity_change(&self, accounts: &InstructionAccounts) -> Option<LiquidityChange> {
+        match self {
+            // dca, user, input_mint, output_mint, user_ata, in_ata, out_ata, ...
+            Self::OpenDca(params) => change(Kind::Deposit, accounts.key(0)?, accounts.key(1)?,
+                vec![accounts.key(2)?, accounts.key(3)?], vec![params.in_amount, 0]),
+            // dca, user, payer, input_mint, output_mint, user_ata, in_ata, out_ata, ...
+            Self::OpenDcaV2(params) => change(Kind::Deposit, accounts.key(0)?, accounts.key(1)?,
+                vec![accounts.key(3)?, accounts.key(4)?], vec![params.in_amount, 0]),
+            // user, dca, in_ata, user_in_ata, ... The output mint is not part
+            // of the instruction, only the input token is recorded.
+            Self::Deposit { deposit_in } => change(Kind::Deposit, accounts.key(1)?, accounts.key(0)?,
+                vec![accounts.mint(2)?], vec![*deposit_in]),
+            // user, dca, input_mint, output_mint, dca_ata, user_in_ata, user_out_ata, ...
+            Self::Withdraw(params) => {
+                let amounts = match params.withdrawal {
+                    Withdrawal::In => vec![params.withdraw_amount, 0],
+                    Withdrawal::Out => vec![0, params.withdraw_amount],
+                };
+                change(Kind::Withdraw, accounts.key(1)?, accounts.key(0)?,
+                    vec![accounts.key(2)?, accounts.key(3)?], amounts)
+            }
+            // user, dca, input_mint, output_mint, in_ata, out_ata, user_in_ata,
+            // user_out_ata, ... The vault token accounts are emptied and closed.
+            Self::CloseDca => change(Kind::Withdraw, accounts.key(1)?, accounts.key(0)?,
+                vec![accounts.key(2)?, accounts.key(3)?],
+                vec![
+                    accounts.balance(4).map_or(0, |c| c.delta()),
+                    accounts.balance(5).map_or(0, |c| c.delta()),
+                ]),
+            Self::InitiateFlashFill | Self::FulfillFlashFill { .. } => None,
+        }
+    }
+}
+
+fn change(kind: Kind, dca: String, user: String, tokens: Vec<String>, amounts: Vec<u64>) -> Option<LiquidityChange> {
+    Some(LiquidityChange {
+        kind: kind as i32,
+        pool: dca.clone(),
+        input_tokens: tokens,
+        input_token_amounts: amounts.iter().map(|amount| amount.to_string()).collect(),
+        output_token: dca,
+        output_token_amount: "0".to_string(),
+        owner: user,
+    })
+}
+
+// Emitted by openDca and openDcaV2
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Opened {
+    pub user_key: [u8; 32],
+    pub dca_key: [u8; 32],
+    pub in_deposited: u64,
+    pub input_mint: [u8; 32],
+    pub output_mint: [u8; 32],
+    pub cycle_frequency: i64,
+    pub in_amount_per_cycle: u64,
+    pub created_at: i64,
+}
+
+// Emitted by fulfillFlashFill once per executed cycle
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Filled {
+    pub user_key: [u8; 32],
+    pub dca_key: [u8; 32],
+    pub input_mint: [u8; 32],
+    pub output_mint: [u8; 32],
+    pub in_amount: u64,
+    pub out_amount: u64,
+    pub fee_mint: [u8; 32],
+    pub fee: u64,
+}
+
+// Emitted by closeDca, with the lifetime totals of the position
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct Closed {
+    pub user_key: [u8; 32],
+    pub dca_key: [u8; 32],
+    pub in_deposited: u64,
+    pub input_mint: [u8; 32],
+    pub output_mint: [u8; 32],
+    pub cycle_frequency: i64,
+    pub in_amount_per_cycle: u64,
+    pub created_at: i64,
+    pub total_in_withdrawn: u64,
+    pub total_out_withdrawn: u64,
+    pub unfilled_amount: u64,
+    pub user_closed: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Opened(Opened),
+    Filled(Filled),
+    Closed(Closed),
+}
+
+impl Event {
+    // Decodes a DCA event from event instruction data, returns None for
+    // other events
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if let Some(mut args) = event_args(data, &OPENED_EVENT) {
+            Opened::deserialize(&mut args).ok().map(Self::Opened)
+        } else if let Some(mut args) = event_args(data, &FILLED_EVENT) {
+            Filled::deserialize(&mut args).ok().map(Self::Filled)
+        } else if let Some(mut args) = event_args(data, &CLOSED_EVENT) {
+            Closed::deserialize(&mut args).ok().map(Self::Closed)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{event_data, instruction_data};
+
+    #[test]
+    fn decodes_open_instructions() {
+        let data = instruction_data(&OPEN_DCA, &[
+            &7u64.to_le_bytes(),        // applicationIdx
+            &1_000u64.to_le_bytes(),    // inAmount
+            &100u64.to_le_bytes(),      // inAmountPerCycle
+            &3_600i64.to_le_bytes(),    // cycleFrequency
+            &[0],                       // minPrice: None
+            &[1], &50u64.to_le_bytes(), // maxPrice: Some
+            &[0],                       // startAt: None
+            &[1, 1],                    // closeWsolInAta: Some(true)
+        ]);
+        assert_eq!(DcaInstruction::decode(&data), Some(DcaInstruction::OpenDca(OpenDcaParams {
+            application_idx: 7,
+            in_amount: 1_000,
+            in_amount_per_cycle: 100,
+            cycle_frequency: 3_600,
+            min_price: None,
+            max_price: Some(50),
+            start_at: None,
+            close_wsol_in_ata: Some(true),
+        })));
+
+        let data = instruction_data(&OPEN_DCA_V2, &[
+            &7u64.to_le_bytes(),
+            &1_000u64.to_le_bytes(),
+            &100u64.to_le_bytes(),
+            &86_400i64.to_le_bytes(),
+            &[1], &20u64.to_le_bytes(),
+            &[0],
+            &[1], &1_700_000_000i64.to_le_bytes(),
+        ]);
+        assert_eq!(DcaInstruction::decode(&data), Some(DcaInstruction::OpenDcaV2(OpenDcaV2Params {
+            application_idx: 7,
+            in_amount: 1_000,
+            in_amount_per_cycle: 100,
+            cycle_frequency: 86_400,
+            min_out_amount: Some(20),
+            max_out_amount: None,
+            start_at: Some(1_700_000_000),
+        })));
+    }
+
+    #[test]
+    fn decodes_position_instructions() {
+        let data = instruction_data(&DEPOSIT, &[&500u64.to_le_bytes()]);
+        assert_eq!(DcaInstruction::decode(&data), Some(DcaInstruction::Deposit { deposit_in: 500 }));
+
+        let data = instruction_data(&WITHDRAW, &[&300u64.to_le_bytes(), &[1]]);
+        assert_eq!(DcaInstruction::decode(&data), Some(DcaInstruction::Withdraw(WithdrawParams {
+            withdraw_amount: 300,
+            withdrawal: Withdrawal::Out,
+        })));
+
+        assert_eq!(DcaInstruction::decode(&CLOSE_DCA), Some(DcaInstruction::CloseDca));
+        assert_eq!(DcaInstruction::decode(&INITIATE_FLASH_FILL), Some(DcaInstruction::InitiateFlashFill));
+
+        let data = instruction_data(&FULFILL_FLASH_FILL, &[&95u64.to_le_bytes()]);
+        assert_eq!(DcaInstruction::decode(&data), Some(DcaInstruction::FulfillFlashFill { repay_amount: 95 }));
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_instructions() {
+        assert_eq!(DcaInstruction::decode(&[0; 16]), None);
+        assert_eq!(DcaInstruction::decode(&DEPOSIT[..4]), None);
+        assert_eq!(DcaInstruction::decode(&instruction_data(&WITHDRAW, &[&300u64.to_le_bytes(), &[2]])), None);
+    }
+
+    #[test]
+    fn decodes_opened_event() {
+        let data = event_data(&OPENED_EVENT, &[
+            &[1; 32],                        // userKey
+            &[2; 32],                        // dcaKey
+            &1_000u64.to_le_bytes(),         // inDeposited
+            &[3; 32],                        // inputMint
+            &[4; 32],                        // outputMint
+            &3_600i64.to_le_bytes(),         // cycleFrequency
+            &100u64.to_le_bytes(),           // inAmountPerCycle
+            &1_700_000_000i64.to_le_bytes(), // createdAt
+        ]);
+        assert_eq!(Event::decode(&data), Some(Event::Opened(Opened {
+            user_key: [1; 32],
+            dca_key: [2; 32],
+            in_deposited: 1_000,
+            input_mint: [3; 32],
+            output_mint: [4; 32],
+            cycle_frequency: 3_600,
+            in_amount_per_cycle: 100,
+            created_at: 1_700_000_000,
+        })));
+    }
+
+    #[test]
+    fn decodes_filled_event() {
+        let data = event_data(&FILLED_EVENT, &[
+            &[1; 32],               // userKey
+            &[2; 32],               // dcaKey
+            &[3; 32],               // inputMint
+            &[4; 32],               // outputMint
+            &100u64.to_le_bytes(),  // inAmount
+            &95u64.to_le_bytes(),   // outAmount
+            &[4; 32],               // feeMint
+            &1u64.to_le_bytes(),    // fee
+        ]);
+        assert_eq!(Event::decode(&data), Some(Event::Filled(Filled {
+            user_key: [1; 32],
+            dca_key: [2; 32],
+            input_mint: [3; 32],
+            output_mint: [4; 32],
+            in_amount: 100,
+            out_amount: 95,
+            fee_mint: [4; 32],
+            fee: 1,
+        })));
+    }
+
+    #[test]
+    fn decodes_closed_event() {
+        let data = event_data(&CLOSED_EVENT, &[
+            &[1; 32],                        // userKey
+            &[2; 32],                        // dcaKey
+            &1_000u64.to_le_bytes(),         // inDeposited
+            &[3; 32],                        // inputMint
+            &[4; 32],                        // outputMint
+            &3_600i64.to_le_bytes(),         // cycleFrequency
+            &100u64.to_le_bytes(),           // inAmountPerCycle
+            &1_700_000_000i64.to_le_bytes(), // createdAt
+            &200u64.to_le_bytes(),           // totalInWithdrawn
+            &760u64.to_le_bytes(),           // totalOutWithdrawn
+            &0u64.to_le_bytes(),             // unfilledAmount
+            &[0],                            // userClosed
+        ]);
+        let Some(Event::Closed(closed)) = Event::decode(&data) else {
+            panic!("not a closed event");
+        };
+        assert_eq!(closed.dca_key, [2; 32]);
+        assert_eq!(closed.total_in_withdrawn, 200);
+        assert_eq!(closed.total_out_withdrawn, 760);
+        assert_eq!(closed.unfilled_amount, 0);
+        assert!(!closed.user_closed);
+    }
+
+    #[test]
+    fn ignores_other_events() {
+        let data = event_data(&crate::events::SWAP_EVENT, &[&[0; 112]]);
+        assert_eq!(Event::decode(&data), None);
+    }
+}
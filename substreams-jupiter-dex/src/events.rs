@@ -0,0 +1,87 @@
+use borsh::BorshDeserialize;
+
+// Anchor `emit_cpi!` events are self-invoked inner instructions whose data
+// starts with the event instruction tag, sha256("anchor:event")[..8] as a
+// little-endian u64, followed by the event discriminator
+pub const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
+
+// sha256("event:SwapEvent")[..8]
+pub const SWAP_EVENT: [u8; 8] = [64, 198, 205, 232, 38, 8, 113, 226];
+
+// Emitted by Jupiter v6 once per executed route hop
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct SwapEvent {
+    pub amm: [u8; 32],
+    pub input_mint: [u8; 32],
+    pub input_amount: u64,
+    pub output_mint: [u8; 32],
+    pub output_amount: u64,
+}
+
+// Returns true if the instruction data is an Anchor self-CPI event
+pub fn is_event_instruction(data: &[u8]) -> bool {
+    data.len() >= 16 && data[..8] == EVENT_IX_TAG
+}
+
+// Args of an event instruction, None unless it carries the given event
+pub fn event_args<'a>(data: &'a [u8], discriminator: &[u8; 8]) -> Option<&'a [u8]> {
+    if !is_event_instruction(data) || data[8..16] != *discriminator {
+        return None;
+    }
+    Some(&data[16..])
+}
+
+// Decodes a SwapEvent from event instruction data, returns None for other events
+pub fn decode_swap_event(data: &[u8]) -> Option<SwapEvent> {
+    SwapEvent::try_from_slice(event_args(data, &SWAP_EVENT)?).ok()
+}
+
+// Instruction data made of a discriminator followed by its borsh encoded args
+#[cfg(test)]
+pub fn instruction_data(discriminator: &[u8; 8], args: &[&[u8]]) -> Vec<u8> {
+    let mut data = discriminator.to_vec();
+    args.iter().for_each(|arg| data.extend_from_slice(arg));
+    data
+}
+
+// Data of the self-CPI instruction emitting the given event
+#[cfg(test)]
+pub fn event_data(discriminator: &[u8; 8], fields: &[&[u8]]) -> Vec<u8> {
+    instruction_data(&EVENT_IX_TAG, &[discriminator, &fields.concat()])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // SwapEvent fields laid out in IDL order: amm, input_mint, input_amount,
+    // output_mint, output_amount
+    fn swap_event_data() -> Vec<u8> {
+        event_data(&SWAP_EVENT, &[&[1; 32], &[2; 32], &1_000u64.to_le_bytes(), &[3; 32], &990u64.to_le_bytes()])
+    }
+
+    #[test]
+    fn decodes_swap_events() {
+        let data = swap_event_data();
+        assert!(is_event_instruction(&data));
+        assert_eq!(decode_swap_event(&data), Some(SwapEvent {
+            amm: [1; 32],
+            input_mint: [2; 32],
+            input_amount: 1_000,
+            output_mint: [3; 32],
+            output_amount: 990,
+        }));
+    }
+
+    #[test]
+    fn rejects_other_and_malformed_events() {
+        let data = swap_event_data();
+        assert_eq!(decode_swap_event(&data[..data.len() - 1]), None);
+        assert_eq!(decode_swap_event(&event_data(&[0; 8], &[&data[16..]])), None);
+
+        // Instruction data that is not an event
+        let route = instruction_data(&crate::instructions::ROUTE, &[&data[16..]]);
+        assert!(!is_event_instruction(&route));
+        assert_eq!(decode_swap_event(&route), None);
+    }
+}
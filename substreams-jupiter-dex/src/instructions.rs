@@ -0,0 +1,596 @@
+use borsh::BorshDeserialize;
+
+// Anchor instruction discriminators for the Jupiter v6 program,
+// computed as sha256("global:<snake_case_name>")[..8]
+pub const ROUTE: [u8; 8] = [229, 23, 203, 151, 122, 227, 173, 42];
+pub const SHARED_ACCOUNTS_ROUTE: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+pub const EXACT_OUT_ROUTE: [u8; 8] = [208, 51, 239, 151, 123, 43, 237, 92];
+pub const SHARED_ACCOUNTS_EXACT_OUT_ROUTE: [u8; 8] = [176, 209, 105, 168, 154, 125, 69, 62];
+pub const ROUTE_WITH_TOKEN_LEDGER: [u8; 8] = [150, 86, 71, 116, 167, 93, 14, 104];
+pub const SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER: [u8; 8] = [230, 121, 143, 80, 119, 159, 106, 170];
+pub const SET_TOKEN_LEDGER: [u8; 8] = [228, 85, 185, 112, 78, 79, 77, 2];
+pub const CREATE_OPEN_ORDERS: [u8; 8] = [229, 194, 212, 172, 8, 10, 134, 147];
+pub const CLAIM: [u8; 8] = [62, 198, 214, 193, 213, 159, 108, 210];
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum AccountsType {
+    TransferHookA,
+    TransferHookB,
+    TransferHookReward,
+    TransferHookInput,
+    TransferHookIntermediate,
+    TransferHookOutput,
+    SupplementalTickArrays,
+    SupplementalTickArraysOne,
+    SupplementalTickArraysTwo,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RemainingAccountsSlice {
+    pub accounts_type: AccountsType,
+    pub length: u8,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RemainingAccountsInfo {
+    pub slices: Vec<RemainingAccountsSlice>,
+}
+
+// Underlying AMM of a route step. Variant order must match the Jupiter v6 IDL,
+// as Borsh encodes the variant as its index.
+#[allow(clippy::enum_variant_names)]
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub enum Swap {
+    Saber,
+    SaberAddDecimalsDeposit,
+    SaberAddDecimalsWithdraw,
+    TokenSwap,
+    Sencha,
+    Step,
+    Cropper,
+    Raydium,
+    Crema { a_to_b: bool },
+    Lifinity,
+    Mercurial,
+    Cykura,
+    Serum { side: Side },
+    MarinadeDeposit,
+    MarinadeUnstake,
+    Aldrin { side: Side },
+    AldrinV2 { side: Side },
+    Whirlpool { a_to_b: bool },
+    Invariant { x_to_y: bool },
+    Meteora,
+    GooseFX,
+    DeltaFi { stable: bool },
+    Balansol,
+    MarcoPolo { x_to_y: bool },
+    Dradex { side: Side },
+    LifinityV2,
+    RaydiumClmm,
+    Openbook { side: Side },
+    Phoenix { side: Side },
+    Symmetry { from_token_id: u64, to_token_id: u64 },
+    TokenSwapV2,
+    HeliumTreasuryManagementRedeemV0,
+    StakeDexStakeWrappedSol,
+    StakeDexSwapViaStake { bridge_stake_seed: u32 },
+    GooseFXV2,
+    Perps,
+    PerpsAddLiquidity,
+    PerpsRemoveLiquidity,
+    MeteoraDlmm,
+    OpenBookV2 { side: Side },
+    RaydiumClmmV2,
+    StakeDexPrefundWithdrawStakeAndDepositStake { bridge_stake_seed: u32 },
+    Clone { pool_index: u8, quantity_is_input: bool, quantity_is_collateral: bool },
+    SanctumS { src_lst_value_calc_accs: u8, dst_lst_value_calc_accs: u8, src_lst_index: u32, dst_lst_index: u32 },
+    SanctumSAddLiquidity { lst_value_calc_accs: u8, lst_index: u32 },
+    SanctumSRemoveLiquidity { lst_value_calc_accs: u8, lst_index: u32 },
+    RaydiumCP,
+    WhirlpoolSwapV2 { a_to_b: bool, remaining_accounts_info: Option<RemainingAccountsInfo> },
+    OneIntro,
+    PumpdotfunWrappedBuy,
+    PumpdotfunWrappedSell,
+    PerpsV2,
+    PerpsV2AddLiquidity,
+    PerpsV2RemoveLiquidity,
+    MoonshotWrappedBuy,
+    MoonshotWrappedSell,
+    StabbleStableSwap,
+    StabbleWeightedSwap,
+    Obric { x_to_y: bool },
+    FoxBuyFromEstimatedCost,
+    FoxClaimPartial { is_y: bool },
+    SolFi { is_quote_to_base: bool },
+    SolayerDelegateNoInit,
+    SolayerUndelegateNoInit,
+    TokenMill { side: Side },
+    DaosFunBuy,
+    DaosFunSell,
+    ZeroFi,
+    StakeDexWithdrawWrappedSol,
+    VirtualsBuy,
+    VirtualsSell,
+    Perena { in_index: u8, out_index: u8 },
+    PumpdotfunAmmBuy,
+    PumpdotfunAmmSell,
+    Gamma,
+}
+
+impl Swap {
+    // Name of the AMM handling the route step
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Saber => "Saber",
+            Self::SaberAddDecimalsDeposit => "SaberAddDecimalsDeposit",
+            Self::SaberAddDecimalsWithdraw => "SaberAddDecimalsWithdraw",
+            Self::TokenSwap => "TokenSwap",
+            Self::Sencha => "Sencha",
+            Self::Step => "Step",
+            Self::Cropper => "Cropper",
+            Self::Raydium => "Raydium",
+            Self::Crema { .. } => "Crema",
+            Self::Lifinity => "Lifinity",
+            Self::Mercurial => "Mercurial",
+            Self::Cykura => "Cykura",
+            Self::Serum { .. } => "Serum",
+            Self::MarinadeDeposit => "MarinadeDeposit",
+            Self::MarinadeUnstake => "MarinadeUnstake",
+            Self::Aldrin { .. } => "Aldrin",
+            Self::AldrinV2 { .. } => "AldrinV2",
+            Self::Whirlpool { .. } => "Whirlpool",
+            Self::Invariant { .. } => "Invariant",
+            Self::Meteora => "Meteora",
+            Self::GooseFX => "GooseFX",
+            Self::DeltaFi { .. } => "DeltaFi",
+            Self::Balansol => "Balansol",
+            Self::MarcoPolo { .. } => "MarcoPolo",
+            Self::Dradex { .. } => "Dradex",
+            Self::LifinityV2 => "LifinityV2",
+            Self::RaydiumClmm => "RaydiumClmm",
+            Self::Openbook { .. } => "Openbook",
+            Self::Phoenix { .. } => "Phoenix",
+            Self::Symmetry { .. } => "Symmetry",
+            Self::TokenSwapV2 => "TokenSwapV2",
+            Self::HeliumTreasuryManagementRedeemV0 => "HeliumTreasuryManagementRedeemV0",
+            Self::StakeDexStakeWrappedSol => "StakeDexStakeWrappedSol",
+            Self::StakeDexSwapViaStake { .. } => "StakeDexSwapViaStake",
+            Self::GooseFXV2 => "GooseFXV2",
+            Self::Perps => "Perps",
+            Self::PerpsAddLiquidity => "PerpsAddLiquidity",
+            Self::PerpsRemoveLiquidity => "PerpsRemoveLiquidity",
+            Self::MeteoraDlmm => "MeteoraDlmm",
+            Self::OpenBookV2 { .. } => "OpenBookV2",
+            Self::RaydiumClmmV2 => "RaydiumClmmV2",
+            Self::StakeDexPrefundWithdrawStakeAndDepositStake { .. } => "StakeDexPrefundWithdrawStakeAndDepositStake",
+            Self::Clone { .. } => "Clone",
+            Self::SanctumS { .. } => "SanctumS",
+            Self::SanctumSAddLiquidity { .. } => "SanctumSAddLiquidity",
+            Self::SanctumSRemoveLiquidity { .. } => "SanctumSRemoveLiquidity",
+            Self::RaydiumCP => "RaydiumCP",
+            Self::WhirlpoolSwapV2 { .. } => "WhirlpoolSwapV2",
+            Self::OneIntro => "OneIntro",
+            Self::PumpdotfunWrappedBuy => "PumpdotfunWrappedBuy",
+            Self::PumpdotfunWrappedSell => "PumpdotfunWrappedSell",
+            Self::PerpsV2 => "PerpsV2",
+            Self::PerpsV2AddLiquidity => "PerpsV2AddLiquidity",
+            Self::PerpsV2RemoveLiquidity => "PerpsV2RemoveLiquidity",
+            Self::MoonshotWrappedBuy => "MoonshotWrappedBuy",
+            Self::MoonshotWrappedSell => "MoonshotWrappedSell",
+            Self::StabbleStableSwap => "StabbleStableSwap",
+            Self::StabbleWeightedSwap => "StabbleWeightedSwap",
+            Self::Obric { .. } => "Obric",
+            Self::FoxBuyFromEstimatedCost => "FoxBuyFromEstimatedCost",
+            Self::FoxClaimPartial { .. } => "FoxClaimPartial",
+            Self::SolFi { .. } => "SolFi",
+            Self::SolayerDelegateNoInit => "SolayerDelegateNoInit",
+            Self::SolayerUndelegateNoInit => "SolayerUndelegateNoInit",
+            Self::TokenMill { .. } => "TokenMill",
+            Self::DaosFunBuy => "DaosFunBuy",
+            Self::DaosFunSell => "DaosFunSell",
+            Self::ZeroFi => "ZeroFi",
+            Self::StakeDexWithdrawWrappedSol => "StakeDexWithdrawWrappedSol",
+            Self::VirtualsBuy => "VirtualsBuy",
+            Self::VirtualsSell => "VirtualsSell",
+            Self::Perena { .. } => "Perena",
+            Self::PumpdotfunAmmBuy => "PumpdotfunAmmBuy",
+            Self::PumpdotfunAmmSell => "PumpdotfunAmmSell",
+            Self::Gamma => "Gamma",
+        }
+    }
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RoutePlanStep {
+    pub swap: Swap,
+    pub percent: u8,
+    pub input_index: u8,
+    pub output_index: u8,
+}
+
+// The route family serializes the variable length `route_plan` before these
+// amounts, so the fixed-size trailing args are decoded from the end of the data.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RouteArgs {
+    pub in_amount: u64,
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ExactOutRouteArgs {
+    pub out_amount: u64,
+    pub quoted_in_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+// The input amount of the token ledger variants is read on-chain from the
+// token ledger account, so it is not part of the instruction data
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RouteWithTokenLedgerArgs {
+    pub quoted_out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct ClaimArgs {
+    pub id: u8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JupiterInstruction {
+    Route {
+        route_plan: Vec<RoutePlanStep>,
+        args: RouteArgs,
+    },
+    SharedAccountsRoute {
+        id: u8,
+        route_plan: Vec<RoutePlanStep>,
+        args: RouteArgs,
+    },
+    ExactOutRoute {
+        route_plan: Vec<RoutePlanStep>,
+        args: ExactOutRouteArgs,
+    },
+    SharedAccountsExactOutRoute {
+        id: u8,
+        route_plan: Vec<RoutePlanStep>,
+        args: ExactOutRouteArgs,
+    },
+    RouteWithTokenLedger {
+        route_plan: Vec<RoutePlanStep>,
+        args: RouteWithTokenLedgerArgs,
+    },
+    SharedAccountsRouteWithTokenLedger {
+        id: u8,
+        route_plan: Vec<RoutePlanStep>,
+        args: RouteWithTokenLedgerArgs,
+    },
+    SetTokenLedger,
+    CreateOpenOrders,
+    Claim(ClaimArgs),
+}
+
+// Quoted amounts of a route instruction, normalised across the exact-in,
+// exact-out and token ledger variants
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapQuote {
+    pub exact_out: bool,
+    pub in_amount: Option<u64>,
+    pub out_amount: u64,
+    pub slippage_bps: u16,
+    pub platform_fee_bps: u8,
+}
+
+impl JupiterInstruction {
+    // Decodes a Jupiter v6 instruction, returns None for unknown
+    // discriminators or malformed args
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let (discriminator, args) = data.split_at(8);
+        match discriminator {
+            d if d == ROUTE => Some(Self::Route {
+                route_plan: decode_route_plan::<RouteArgs>(args),
+                args: decode_tail(args)?,
+            }),
+            d if d == SHARED_ACCOUNTS_ROUTE => Some(Self::SharedAccountsRoute {
+                id: *args.first()?,
+                route_plan: decode_route_plan::<RouteArgs>(&args[1..]),
+                args: decode_tail(args)?,
+            }),
+            d if d == EXACT_OUT_ROUTE => Some(Self::ExactOutRoute {
+                route_plan: decode_route_plan::<ExactOutRouteArgs>(args),
+                args: decode_tail(args)?,
+            }),
+            d if d == SHARED_ACCOUNTS_EXACT_OUT_ROUTE => Some(Self::SharedAccountsExactOutRoute {
+                id: *args.first()?,
+                route_plan: decode_route_plan::<ExactOutRouteArgs>(&args[1..]),
+                args: decode_tail(args)?,
+            }),
+            d if d == ROUTE_WITH_TOKEN_LEDGER => Some(Self::RouteWithTokenLedger {
+                route_plan: decode_route_plan::<RouteWithTokenLedgerArgs>(args),
+                args: decode_tail(args)?,
+            }),
+            d if d == SHARED_ACCOUNTS_ROUTE_WITH_TOKEN_LEDGER => {
+                Some(Self::SharedAccountsRouteWithTokenLedger {
+                    id: *args.first()?,
+                    route_plan: decode_route_plan::<RouteWithTokenLedgerArgs>(&args[1..]),
+                    args: decode_tail(args)?,
+                })
+            }
+            d if d == SET_TOKEN_LEDGER => Some(Self::SetTokenLedger),
+            d if d == CREATE_OPEN_ORDERS => Some(Self::CreateOpenOrders),
+            d if d == CLAIM => ClaimArgs::try_from_slice(args).ok().map(Self::Claim),
+            _ => None,
+        }
+    }
+
+    // Instruction name as it appears in the Jupiter v6 IDL
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Route { .. } => "route",
+            Self::SharedAccountsRoute { .. } => "sharedAccountsRoute",
+            Self::ExactOutRoute { .. } => "exactOutRoute",
+            Self::SharedAccountsExactOutRoute { .. } => "sharedAccountsExactOutRoute",
+            Self::RouteWithTokenLedger { .. } => "routeWithTokenLedger",
+            Self::SharedAccountsRouteWithTokenLedger { .. } => "sharedAccountsRouteWithTokenLedger",
+            Self::SetTokenLedger => "setTokenLedger",
+            Self::CreateOpenOrders => "createOpenOrders",
+            Self::Claim(_) => "claim",
+        }
+    }
+
+    // Returns the quoted amounts for the swapping instructions
+    pub fn quote(&self) -> Option<SwapQuote> {
+        match self {
+            Self::Route { args, .. } | Self::SharedAccountsRoute { args, .. } => Some(SwapQuote {
+                exact_out: false,
+                in_amount: Some(args.in_amount),
+                out_amount: args.quoted_out_amount,
+                slippage_bps: args.slippage_bps,
+                platform_fee_bps: args.platform_fee_bps,
+            }),
+            Self::ExactOutRoute { args, .. } | Self::SharedAccountsExactOutRoute { args, .. } => {
+                Some(SwapQuote {
+                    exact_out: true,
+                    in_amount: Some(args.quoted_in_amount),
+                    out_amount: args.out_amount,
+                    slippage_bps: args.slippage_bps,
+                    platform_fee_bps: args.platform_fee_bps,
+                })
+            }
+            Self::RouteWithTokenLedger { args, .. }
+            | Self::SharedAccountsRouteWithTokenLedger { args, .. } => Some(SwapQuote {
+                exact_out: false,
+                in_amount: None,
+                out_amount: args.quoted_out_amount,
+                slippage_bps: args.slippage_bps,
+                platform_fee_bps: args.platform_fee_bps,
+            }),
+            Self::SetTokenLedger | Self::CreateOpenOrders | Self::Claim(_) => None,
+        }
+    }
+
+    // Route plan of the swapping instructions, empty for the others
+    pub fn route_plan(&self) -> &[RoutePlanStep] {
+        match self {
+            Self::Route { route_plan, .. }
+            | Self::SharedAccountsRoute { route_plan, .. }
+            | Self::ExactOutRoute { route_plan, .. }
+            | Self::SharedAccountsExactOutRoute { route_plan, .. }
+            | Self::RouteWithTokenLedger { route_plan, .. }
+            | Self::SharedAccountsRouteWithTokenLedger { route_plan, .. } => route_plan,
+            Self::SetTokenLedger | Self::CreateOpenOrders | Self::Claim(_) => &[],
+        }
+    }
+
+    // Account layout of the route instructions, None for the other instructions
+    pub fn account_layout(&self) -> Option<SwapAccountLayout> {
+        match self {
+            // token_program, user_transfer_authority, user_source_token_account,
+            // user_destination_token_account, destination_token_account, ...
+            Self::Route { .. } | Self::ExactOutRoute { .. } | Self::RouteWithTokenLedger { .. } => Some(SwapAccountLayout {
+                user_transfer_authority: 1,
+                destination_token_account: 4,
+                user_destination_token_account: Some(3),
+            }),
+            // token_program, program_authority, user_transfer_authority,
+            // source_token_account, program_source_token_account,
+            // program_destination_token_account, destination_token_account, ...
+            Self::SharedAccountsRoute { .. }
+            | Self::SharedAccountsExactOutRoute { .. }
+            | Self::SharedAccountsRouteWithTokenLedger { .. } => Some(SwapAccountLayout {
+                user_transfer_authority: 2,
+                destination_token_account: 6,
+                user_destination_token_account: None,
+            }),
+            Self::SetTokenLedger | Self::CreateOpenOrders | Self::Claim(_) => None,
+        }
+    }
+}
+
+// Positions of the user accounts in a route instruction's account list
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapAccountLayout {
+    pub user_transfer_authority: usize,
+    pub destination_token_account: usize,
+    // Receives the output when the optional destination_token_account is
+    // omitted, which Anchor encodes as the Jupiter program id
+    pub user_destination_token_account: Option<usize>,
+}
+
+// Fixed-size argument structs that trail the route plan
+trait TrailingArgs: BorshDeserialize {
+    const LEN: usize;
+}
+
+impl TrailingArgs for RouteArgs {
+    const LEN: usize = 8 + 8 + 2 + 1;
+}
+
+impl TrailingArgs for ExactOutRouteArgs {
+    const LEN: usize = 8 + 8 + 2 + 1;
+}
+
+impl TrailingArgs for RouteWithTokenLedgerArgs {
+    const LEN: usize = 8 + 2 + 1;
+}
+
+// Decodes the trailing args from the last bytes of the instruction data
+fn decode_tail<T: TrailingArgs>(args: &[u8]) -> Option<T> {
+    if args.len() < T::LEN {
+        return None;
+    }
+    T::try_from_slice(&args[args.len() - T::LEN..]).ok()
+}
+
+// Decodes the route plan that precedes the trailing args `T`. Steps using an
+// AMM this decoder does not know yet fail the whole vector, in which case the
+// plan is left empty and only the trailing args are kept.
+fn decode_route_plan<T: TrailingArgs>(args: &[u8]) -> Vec<RoutePlanStep> {
+    let mut buf = args;
+    match Vec::<RoutePlanStep>::deserialize(&mut buf) {
+        Ok(route_plan) if buf.len() == T::LEN => route_plan,
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::instruction_data;
+
+    // RouteArgs: in_amount, quoted_out_amount, slippage_bps, platform_fee_bps
+    fn route_args(in_amount: u64, quoted_out_amount: u64) -> Vec<u8> {
+        [&in_amount.to_le_bytes()[..], &quoted_out_amount.to_le_bytes(), &50u16.to_le_bytes(), &[20]].concat()
+    }
+
+    #[test]
+    fn decodes_the_trailing_args() {
+        let args = [&[0xff; 5][..], &route_args(1_000, 990)].concat();
+        assert_eq!(decode_tail::<RouteArgs>(&args), Some(RouteArgs {
+            in_amount: 1_000,
+            quoted_out_amount: 990,
+            slippage_bps: 50,
+            platform_fee_bps: 20,
+        }));
+        assert_eq!(decode_tail::<RouteArgs>(&args[..RouteArgs::LEN - 1]), None);
+    }
+
+    #[test]
+    fn decodes_the_quote_of_each_route_variant() {
+        let empty_plan = 0u32.to_le_bytes();
+
+        let data = instruction_data(&ROUTE, &[&empty_plan, &route_args(1_000, 990)]);
+        let instruction = JupiterInstruction::decode(&data).unwrap();
+        assert_eq!(instruction.name(), "route");
+        assert_eq!(instruction.quote(), Some(SwapQuote {
+            exact_out: false,
+            in_amount: Some(1_000),
+            out_amount: 990,
+            slippage_bps: 50,
+            platform_fee_bps: 20,
+        }));
+
+        // out_amount, quoted_in_amount, slippage_bps, platform_fee_bps
+        let data = instruction_data(&SHARED_ACCOUNTS_EXACT_OUT_ROUTE, &[&[3], &empty_plan, &route_args(500, 510)]);
+        let instruction = JupiterInstruction::decode(&data).unwrap();
+        assert!(matches!(instruction, JupiterInstruction::SharedAccountsExactOutRoute { id: 3, .. }));
+        assert_eq!(instruction.quote(), Some(SwapQuote {
+            exact_out: true,
+            in_amount: Some(510),
+            out_amount: 500,
+            slippage_bps: 50,
+            platform_fee_bps: 20,
+        }));
+
+        // The input amount of the token ledger variants is not in the data
+        let data = instruction_data(&ROUTE_WITH_TOKEN_LEDGER, &[&empty_plan, &990u64.to_le_bytes(), &50u16.to_le_bytes(), &[0]]);
+        let quote = JupiterInstruction::decode(&data).unwrap().quote().unwrap();
+        assert_eq!(quote.in_amount, None);
+        assert_eq!(quote.out_amount, 990);
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_instructions() {
+        assert_eq!(JupiterInstruction::decode(&[0; 7]), None);
+        assert_eq!(JupiterInstruction::decode(&[0; 8]), None);
+        assert_eq!(JupiterInstruction::decode(&instruction_data(&ROUTE, &[&[0; 4]])), None);
+        assert_eq!(JupiterInstruction::decode(&instruction_data(&CLAIM, &[&[2]])),
+            Some(JupiterInstruction::Claim(ClaimArgs { id: 2 })));
+        assert_eq!(JupiterInstruction::decode(&SET_TOKEN_LEDGER).unwrap().quote(), None);
+    }
+
+    // Two step plan: 60% through Raydium (variant 7), then 40% through
+    // Whirlpool (variant 17) with a_to_b set
+    fn route_plan() -> Vec<u8> {
+        [&2u32.to_le_bytes()[..], &[7, 60, 0, 1], &[17, 1, 40, 0, 1]].concat()
+    }
+
+    #[test]
+    fn decodes_the_route_plan() {
+        let data = instruction_data(&SHARED_ACCOUNTS_ROUTE, &[&[3], &route_plan(), &route_args(1_000, 990)]);
+        let instruction = JupiterInstruction::decode(&data).unwrap();
+        assert_eq!(instruction.route_plan(), &[
+            RoutePlanStep { swap: Swap::Raydium, percent: 60, input_index: 0, output_index: 1 },
+            RoutePlanStep { swap: Swap::Whirlpool { a_to_b: true }, percent: 40, input_index: 0, output_index: 1 },
+        ]);
+        let labels: Vec<&str> = instruction.route_plan().iter().map(|step| step.swap.label()).collect();
+        assert_eq!(labels, vec!["Raydium", "Whirlpool"]);
+    }
+
+    #[test]
+    fn keeps_the_quote_of_plans_with_unknown_amms() {
+        let plan = [&1u32.to_le_bytes()[..], &[255, 100, 0, 1]].concat();
+        let data = instruction_data(&ROUTE, &[&plan, &route_args(1_000, 990)]);
+        let instruction = JupiterInstruction::decode(&data).unwrap();
+        assert!(instruction.route_plan().is_empty());
+        assert_eq!(instruction.quote().unwrap().in_amount, Some(1_000));
+
+        // A plan that does not end right before the trailing args is dropped
+        let args = [&route_plan()[..], &[0], &route_args(1_000, 990)].concat();
+        assert!(decode_route_plan::<RouteArgs>(&args).is_empty());
+    }
+
+    #[test]
+    fn locates_the_user_accounts_of_each_route_variant() {
+        let empty_plan = 0u32.to_le_bytes();
+        let layout = |discriminator: &[u8; 8], id: &[u8]| {
+            let data = instruction_data(discriminator, &[id, &empty_plan, &route_args(1_000, 990)]);
+            JupiterInstruction::decode(&data).unwrap().account_layout()
+        };
+
+        // The destination_token_account of the plain variants is optional and
+        // falls back to user_destination_token_account
+        let plain = Some(SwapAccountLayout {
+            user_transfer_authority: 1,
+            destination_token_account: 4,
+            user_destination_token_account: Some(3),
+        });
+        assert_eq!(layout(&ROUTE, &[]), plain);
+        assert_eq!(layout(&EXACT_OUT_ROUTE, &[]), plain);
+
+        let shared = Some(SwapAccountLayout {
+            user_transfer_authority: 2,
+            destination_token_account: 6,
+            user_destination_token_account: None,
+        });
+        assert_eq!(layout(&SHARED_ACCOUNTS_ROUTE, &[1]), shared);
+        assert_eq!(layout(&SHARED_ACCOUNTS_EXACT_OUT_ROUTE, &[1]), shared);
+
+        assert_eq!(JupiterInstruction::decode(&CREATE_OPEN_ORDERS).unwrap().account_layout(), None);
+    }
+}
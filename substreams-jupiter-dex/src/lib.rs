@@ -1,144 +1,103 @@
+// The handler macro turns string params into raw pointer arguments
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use std::collections::{BTreeMap, BTreeSet};
+
 use substreams::errors::Error;
 use substreams::log;
+use substreams::pb::sf::substreams::index::v1::Keys;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::pb::substreams::Clock;
+use substreams::scalar::BigInt;
+use substreams::store::{
+    DeltaInt64, DeltaProto, Deltas, StoreAdd, StoreAddBigInt, StoreDelete, StoreAddInt64, StoreGet, StoreGetBigInt,
+    StoreGetInt64, StoreNew, StoreSet, StoreSetIfNotExists, StoreSetIfNotExistsInt64, StoreSetIfNotExistsProto,
+    StoreSetProto,
+};
 use substreams_entity_change::pb::entity::EntityChanges;
 use substreams_entity_change::tables::Tables;
-use substreams_solana::pb::sf::solana::r#type::v1::Block;
+use substreams_solana::block_view::InstructionView;
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, TransactionStatusMeta};
 
+mod accounts;
+mod balances;
+mod dca;
+mod events;
+mod instructions;
+mod limit_order;
+mod params;
 mod pb;
+mod perps;
+mod programs;
 
-// Jupiter Program IDs
-const JUPITER_AMMS: &[&str] = &[
-    // Core programs
-    "JUP4Fb2cqiRUcaTHdrPC8h2g7yFhLMZB19XWN5Q7McL",  // Core
-    "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",  // v6
-    "JUP2jxvXaqu7NQY1GmNF4m1vodw12LVXYxbFL2uJvfo",  // v2
-    "JUP3c2Uh3WA4Ng34tw6kPd2G4C5BB21Xo36Je1s32Ph",  // v3
-    "JUP5cHjnnCx2DppVsufsHpwj7mZb8GKsTNh6T8mnzFf",  // v4
-    
-    // Additional programs
-    "JUPNZLmBYhqApWWb9YrKvNsaGWxSqWpLh5FhqgfVKKfk",  // Versioned
-    "JUP7th7h8x5RhKXEiWxzHpf8KoZGstuFKHGtszxYEyF",   // Limit Order
-    "JUPGShgHoH5ZXMQy7mZ3FSRqbMrRJSZo3qUdxgA5LBJ",   // Stats
-    "JUPcgE9dCJAExe3zQYzDmGHcg5JBnFLRWJcUqXn5B59",   // Price Feed
-];
-
-// Known Jupiter method IDs
-const JUPITER_METHOD_IDS: &[(&str, &str)] = &[
-    // Core methods
-    ("d6eb2e64", "route"),           // route(RouteParams)
-    ("d5eca8f9", "exactInput"),      // exactInput(ExactInputParams)
-    ("f44947b3", "exactOutput"),     // exactOutput(ExactOutputParams)
-    
-    // Token ledger methods
-    ("f7286c7c", "routeWithTokenLedger"), // routeWithTokenLedger(RouteWithTokenLedgerParams)
-    ("e31ed638", "setTokenLedger"),  // setTokenLedger(TokenLedgerParams)
-    
-    // Versioned methods
-    ("b5b3f362", "routeV2"),         // routeV2(RouteV2Params)
-    ("a7e338e4", "exactInputV2"),    // exactInputV2(ExactInputV2Params)
-    ("cb9124e4", "exactOutputV2"),   // exactOutputV2(ExactOutputV2Params)
-    
-    // Limit order methods
-    ("c3b3f38a", "placeLimitOrder"), // placeLimitOrder(LimitOrderParams)
-    ("d8a42e86", "cancelLimitOrder") // cancelLimitOrder(LimitOrderId)
-];
+use accounts::{InstructionAccounts, ResolvedAccounts};
+use balances::{raw_token_amount, BalanceChanges, TokenAccountChange};
+use dca::DcaInstruction;
+use instructions::JupiterInstruction;
+use limit_order::LimitOrderInstruction;
+use params::{LogLevel, Params};
+use programs::{ProgramKind, ProgramSet};
+use pb::sf::jupiter::v1::{
+    dca_event, limit_order_event, liquidity_change::Kind, perp_event, DcaEvent, JupiterTransaction, JupiterTransactions,
+    LimitOrderEvent, LiquidityChange, PerpEvent, Pool, RouteStep, SwapEvent, SwapQuote, TokenBalanceChange,
+    UsageSnapshot,
+};
+use perps::PerpsInstruction;
 
-// Helper function to get Jupiter method type
-fn get_jupiter_method_type(method_id: &[u8]) -> &'static str {
-    if method_id.len() < 4 {
-        return "Unknown";
-    }
-    
-    let method_hex = hex::encode(&method_id[..4]);
-    for (id, name) in JUPITER_METHOD_IDS {
-        if method_hex.starts_with(id) {
-            return name;
-        }
-    }
-    "Unknown"
-}
-
-// Helper function to get Jupiter program type
-fn get_jupiter_program_type(program_id: &str) -> &'static str {
-    if program_id.starts_with("JUP6") {
-        "v6"
-    } else if program_id.starts_with("JUP4") {
-        "Core"
-    } else if program_id.starts_with("JUP2") {
-        "v2"
-    } else if program_id.starts_with("JUP3") {
-        "v3"
-    } else if program_id.starts_with("JUP5") {
-        "v4"
-    } else if program_id.starts_with("JUPN") {
-        "Versioned"
-    } else if program_id.starts_with("JUP7") {
-        "Limit Order"
-    } else if program_id.starts_with("JUPG") {
-        "Stats"
-    } else if program_id.starts_with("JUPc") {
-        "Price Feed"
-    } else {
-        "Unknown"
-    }
-}
+const SECONDS_PER_DAY: u64 = 86400;
 
-// Target transaction details
-const TARGET_TX_STR: &str = "2k9An8rvpYHcECgtNQUJJRkZwqXWHBZpbbMcYoFaS8vov4ZoUEFKRiQy73Bjx3Adc7WzXMaF163vWqKakV76hB4V";
-const TARGET_BLOCK: u64 = 322167085;
+// Key every block by the Jupiter programs its successful transactions load,
+// plus a `jupiter` key for blocks touching any of them. The map modules filter
+// on the latter to skip blocks without Jupiter activity.
+#[substreams::handlers::map]
+pub fn index_jupiter_programs(params: String, block: Block) -> Result<Keys, Error> {
+    let params = Params::parse(&params)?;
+    let mut keys: Vec<String> = Vec::new();
 
-fn is_target_transaction(raw_sig: &[u8], block_slot: u64) -> bool {
-    // First check block number
-    if block_slot != TARGET_BLOCK {
-        return false;
+    for tx in block.transactions() {
+        for account in ResolvedAccounts::from_transaction(tx).iter() {
+            if let Some(program) = params.programs.get(account) {
+                let key = format!("program:{}", program.id);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
     }
 
-    // Then check signature if block matches
-    if let Ok(target_bytes) = bs58::decode(TARGET_TX_STR).into_vec() {
-        raw_sig == target_bytes.as_slice()
-    } else {
-        false
+    if !keys.is_empty() {
+        keys.push("jupiter".to_string());
     }
+
+    Ok(Keys { keys })
 }
 
 #[substreams::handlers::map]
-pub fn map_jupiter_trades(block: Block) -> Result<EntityChanges, Error> {
-    let mut tables = Tables::new();
+pub fn map_jupiter_transactions(params: String, block: Block) -> Result<JupiterTransactions, Error> {
+    let params = Params::parse(&params)?;
+    let mut transactions = Vec::new();
 
-    log::info!("Processing block:");
-    log::info!("- Slot: {} (target: {}, match: {})", 
-        block.slot, TARGET_BLOCK, block.slot == TARGET_BLOCK);
-    
-    // Use safer handling of blockhash
-    log::info!("- Hash: {}", bs58::encode(&block.blockhash).into_string());
-    log::info!("- Parent Hash: {}", bs58::encode(&block.previous_blockhash).into_string());
-    log::info!("- Timestamp: {}", block.block_time.as_ref().map_or("Unknown".to_string(), |bt| bt.timestamp.to_string()));
-    log::info!("- Transactions: {}", block.transactions.len());
-    log::info!("- Failed Transactions: {}", block.transactions.iter()
-        .filter(|tx| tx.meta.as_ref().and_then(|m| m.err.as_ref()).is_some())
-        .count());
-
-    log::info!("Creating protocol entities for Jupiter AMM programs:");
-    for &program_id in JUPITER_AMMS {
-        let program_type = get_jupiter_program_type(program_id);
-        log::info!("Creating protocol entity:");
-        log::info!("- ID: {}", program_id);
-        log::info!("- Type: {}", program_type);
-        log::info!("- Initial Stats:");
-        log::info!("  * Unique Users: 0");
-        log::info!("  * Pool Count: 0");
+    if params.logs(LogLevel::Info) {
+        log::info!("Processing block:");
+        log::info!("- Slot: {}", block.slot);
         
-        let protocol = tables.create_row("Protocol", program_id);
-        protocol.set("id", program_id);
-        protocol.set("cumulativeUniqueUsers", 0i64);
-        protocol.set("totalPoolCount", 0i64);
+        // Use safer handling of blockhash
+        log::info!("- Hash: {}", bs58::encode(&block.blockhash).into_string());
+        log::info!("- Parent Hash: {}", bs58::encode(&block.previous_blockhash).into_string());
+        log::info!("- Timestamp: {}", block.block_time.as_ref().map_or("Unknown".to_string(), |bt| bt.timestamp.to_string()));
+        log::info!("- Transactions: {}", block.transactions.len());
+        log::info!("- Failed Transactions: {}", block.transactions.iter()
+            .filter(|tx| tx.meta.as_ref().and_then(|m| m.err.as_ref()).is_some())
+            .count());
     }
 
     // Use Block::transactions() helper for safer iteration over successful transactions
     for (tx_idx, tx) in block.transactions.iter().enumerate() {
         // Skip failed transactions early
         if tx.meta.as_ref().and_then(|m| m.err.as_ref()).is_some() {
-            log::info!("Skipping failed transaction {}", tx_idx);
+            if params.logs(LogLevel::Debug) {
+                log::info!("Skipping failed transaction {}", tx_idx);
+            }
             continue;
         }
         
@@ -148,33 +107,29 @@ pub fn map_jupiter_trades(block: Block) -> Result<EntityChanges, Error> {
             .map(|sig| sig.as_slice())
             .unwrap_or(&[]);
             
-        // Use safer handling
-        let tx_signature = if !raw_sig.is_empty() {
-            bs58::encode(raw_sig).into_string()
-        } else {
-            "unknown".to_string()
-        };
-        
-        let is_target = is_target_transaction(raw_sig, block.slot);
+        let accounts = ResolvedAccounts::from_transaction(tx);
+        let is_traced = params.is_traced(raw_sig, block.slot, accounts.signer());
         
         // Log signature details
-        log::info!("Processing transaction {} with signature {} (target: {}, match: {})", 
-            tx_idx, tx_signature, TARGET_TX_STR, is_target);
+        if params.logs(LogLevel::Debug) {
+            log::info!("Processing transaction {} with signature {} (traced: {})", 
+                tx_idx, encode_signature(raw_sig), is_traced);
+        }
         
         if let Some(meta) = &tx.meta {
-            // Log token balances if this is our target transaction
-            if is_target_transaction(raw_sig, block.slot) {
-                log::info!("Target transaction token balances:");
+            // Log token balances if this transaction is traced
+            if is_traced {
+                log::info!("Traced transaction {} token balances:", encode_signature(raw_sig));
                 for (idx, balance) in meta.pre_token_balances.iter().enumerate() {
                     if balance.mint.is_empty() {
                         continue; // Skip entries with empty mint addresses
                     }
                     
-                    log::info!("Pre balance {}: Mint: {}, Owner: {}, Amount: {:?}", 
+                    log::info!("Pre balance {}: Mint: {}, Owner: {}, Amount: {}", 
                         idx,
-                        bs58::encode(&balance.mint).into_string(),
-                        bs58::encode(&balance.owner).into_string(),
-                        balance.ui_token_amount.as_ref().map(|a| a.ui_amount)
+                        balance.mint,
+                        balance.owner,
+                        raw_token_amount(balance)
                     );
                 }
                 
@@ -183,87 +138,819 @@ pub fn map_jupiter_trades(block: Block) -> Result<EntityChanges, Error> {
                         continue; // Skip entries with empty mint addresses
                     }
                     
-                    log::info!("Post balance {}: Mint: {}, Owner: {}, Amount: {:?}",
+                    log::info!("Post balance {}: Mint: {}, Owner: {}, Amount: {}",
                         idx,
-                        bs58::encode(&balance.mint).into_string(),
-                        bs58::encode(&balance.owner).into_string(),
-                        balance.ui_token_amount.as_ref().map(|a| a.ui_amount)
+                        balance.mint,
+                        balance.owner,
+                        raw_token_amount(balance)
                     );
                 }
             }
 
-            if let Some(transaction) = &tx.transaction {
-                if let Some(message) = &transaction.message {
-                    // Process all instructions (main and inner) using walk_instructions
-                    for instruction_view in tx.walk_instructions() {
-                        let program_id_str = instruction_view.program_id().to_string();
-                        
-                        // Check if this is a Jupiter AMM program
-                        let is_jupiter_amm = JUPITER_AMMS.contains(&program_id_str.as_str());
-                        
-                        // Also check if any account in the instruction references Jupiter AMMs
-                        let has_jupiter_account = instruction_view.accounts()
-                            .iter()
-                            .any(|account| JUPITER_AMMS.contains(&account.to_string().as_str()));
-                        
-                        if is_jupiter_amm || has_jupiter_account {
-                            log::info!("Found Jupiter program in instruction:");
-                            log::info!("- Program ID: {}", program_id_str);
-                            log::info!("- Transaction: {}", tx_signature);
+            if tx.transaction.as_ref().and_then(|t| t.message.as_ref()).is_some() {
+                let context = TransactionContext { tx_index: tx_idx, accounts, is_traced };
+                let mut tx_transactions: Vec<JupiterTransaction> = Vec::new();
+
+                // Position of the instruction within the transaction, inner_index
+                // is None for the outer (compiled) instruction itself
+                let mut outer_index: u32 = 0;
+                let mut inner_index: Option<u32> = None;
+
+                // Stack height of the Jupiter instruction being executed, the
+                // instructions above it are its CPIs
+                let mut jupiter_height: Option<u32> = None;
+
+                // Process all instructions (main and inner) using walk_instructions
+                for (position, instruction_view) in tx.walk_instructions().enumerate() {
+                    if instruction_view.is_root() {
+                        if position > 0 {
+                            outer_index += 1;
+                        }
+                        inner_index = None;
+                    } else {
+                        inner_index = Some(inner_index.map_or(0, |i| i + 1));
+                    }
+
+                    let height = if instruction_view.is_root() { 1 } else { instruction_view.stack_height() };
+                    if jupiter_height.map_or(false, |h| height <= h) {
+                        jupiter_height = None;
+                    }
+
+                    let program_id = instruction_view.program_id();
+                    let is_jupiter_program = params.programs.contains(program_id.0);
+                    let is_event = events::is_event_instruction(instruction_view.data());
+
+                    match classify_instruction(jupiter_height, is_jupiter_program, is_event) {
+                        InstructionClass::Outer => {
+                            jupiter_height = Some(height);
+
+                            if params.logs(LogLevel::Info) {
+                                log::info!("Found Jupiter program in instruction:");
+                                log::info!("- Program ID: {}", program_id);
+                                log::info!("- Transaction: {}", encode_signature(raw_sig));
+                            }
                             
-                            // Log instruction data if this is our target transaction
-                            if is_target_transaction(raw_sig, block.slot) {
-                                log::info!("Target tx instruction data: 0x{}", hex::encode(instruction_view.data()));
-                                
-                                // Check if this looks like a swap instruction
-                                if instruction_view.data().len() >= 8 {
-                                    let method_id = &instruction_view.data()[..8];
-                                    let method_type = get_jupiter_method_type(method_id);
-                                    log::info!("Found Jupiter instruction: {}", method_type);
+                            // Log instruction data if this transaction is traced
+                            if is_traced {
+                                log::info!("Traced tx instruction data: 0x{}", hex::encode(instruction_view.data()));
+                            }
+
+                            tx_transactions.push(process_jupiter_instruction(&instruction_view, &context, outer_index, inner_index, &block, &params));
+                        }
+                        InstructionClass::Event => {
+                            // Events belong to the enclosing instruction of the emitting program
+                            let Some(jupiter_transaction) = tx_transactions.last_mut()
+                                .filter(|t| t.program_id == *program_id.0) else {
+                                if is_traced {
+                                    log::info!("Skipping {} event without an enclosing instruction", program_id);
                                 }
+                                continue;
+                            };
+
+                            // Jupiter v6 records every executed hop as a self-CPI SwapEvent,
+                            // DCA and Perps the changes of their positions
+                            let data = instruction_view.data();
+                            let inner = inner_index.unwrap_or(0);
+                            match params.programs.kind(program_id.0) {
+                                Some(ProgramKind::AggregatorV6 | ProgramKind::AggregatorV4) => {
+                                    if let Some(event) = events::decode_swap_event(data) {
+                                        jupiter_transaction.swap_events.push(to_swap_event(&event, outer_index, inner));
+                                    }
+                                }
+                                Some(ProgramKind::Dca) => {
+                                    // Cycles are filled by a keeper, the position belongs to its user
+                                    if let Some(event) = dca::Event::decode(data) {
+                                        let event = to_dca_event(&event, outer_index, inner);
+                                        jupiter_transaction.trader = event.user.clone();
+                                        jupiter_transaction.dca_events.push(event);
+                                    }
+                                }
+                                Some(ProgramKind::Perps) => {
+                                    // Requests are executed by a keeper, the position belongs to its
+                                    // owner. Liquidation events carry no owner and keep the trader
+                                    // cleared by liquidateFullPosition4.
+                                    if let Some(event) = perps::Event::decode(data) {
+                                        let event = to_perp_event(&event, outer_index, inner);
+                                        if !event.owner.is_empty() {
+                                            jupiter_transaction.trader = event.owner.clone();
+                                        }
+                                        jupiter_transaction.perp_events.push(event);
+                                    }
+                                }
+                                Some(ProgramKind::LimitOrder) | None => {}
+                            }
+                        }
+                        InstructionClass::Cpi => {
+                            // AMM and token program CPIs are accounted for by the
+                            // SwapEvents and the balance changes of the transaction
+                            if is_traced {
+                                log::info!("Jupiter CPI into {} at stack height {}", program_id, height);
                             }
-                            
-                            process_jupiter_instruction(tx, transaction, message, &program_id_str, &mut tables, &block)?;
                         }
+                        InstructionClass::Other => {}
                     }
                 }
+
+                flag_product_fills(&mut tx_transactions, &params.programs);
+                transactions.extend(tx_transactions);
             }
         }
     }
 
+    if params.logs(LogLevel::Info) {
+        log::info!("Found {} Jupiter instructions", transactions.len());
+    }
+
+    Ok(JupiterTransactions { transactions })
+}
+
+// Mark each trader the first time they use a protocol, keyed by
+// `{protocol}:{trader}`. Only first-time traders produce a delta.
+#[substreams::handlers::store]
+pub fn store_unique_users(transactions: JupiterTransactions, store: StoreSetIfNotExistsInt64) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        if jupiter_transaction.trader.is_empty() {
+            continue;
+        }
+
+        let protocol = bs58::encode(&jupiter_transaction.program_id).into_string();
+        store.set_if_not_exists(ordinal as u64, format!("{}:{}", protocol, jupiter_transaction.trader), &1);
+    }
+}
+
+// Count the first-time traders of every protocol, keyed by `{protocol}`
+#[substreams::handlers::store]
+pub fn store_protocol_unique_users(unique_users: Deltas<DeltaInt64>, store: StoreAddInt64) {
+    for delta in unique_users.deltas.iter().filter(|d| d.operation == Operation::Create) {
+        store.add(delta.ordinal, substreams::key::first_segment(&delta.key), 1);
+    }
+}
+
+// Register every pool the first time a swap goes through it, keyed by pool
+// id. Limit orders, DCA positions and JLP custodies are not pools, so
+// deposits and withdrawals never register one.
+#[substreams::handlers::store]
+pub fn store_pools(transactions: JupiterTransactions, store: StoreSetIfNotExistsProto<Pool>) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        let Some(pool_id) = pool_id(jupiter_transaction) else {
+            continue;
+        };
+
+        store.set_if_not_exists(ordinal as u64, &pool_id, &Pool {
+            id: pool_id.clone(),
+            protocol: bs58::encode(&jupiter_transaction.program_id).into_string(),
+            input_tokens: vec![jupiter_transaction.base_mint.clone(), jupiter_transaction.quote_mint.clone()],
+            created_slot: jupiter_transaction.slot,
+            created_timestamp: jupiter_transaction.timestamp,
+        });
+    }
+}
+
+// Count the pools of every protocol, keyed by `{protocol}`
+#[substreams::handlers::store]
+pub fn store_protocol_pool_count(pools: Deltas<DeltaProto<Pool>>, store: StoreAddInt64) {
+    for delta in pools.deltas.iter().filter(|d| d.operation == Operation::Create) {
+        store.add(delta.ordinal, &delta.new_value.protocol, 1);
+    }
+}
+
+// Accumulate the traded native-unit amounts of every pool, keyed by
+// `{pool}:{token index}` in the order of the pool's input tokens
+#[substreams::handlers::store]
+pub fn store_pool_volumes(transactions: JupiterTransactions, store: StoreAddBigInt) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        let Some(pool_id) = pool_id(jupiter_transaction) else {
+            continue;
+        };
+
+        let amount_in = BigInt::try_from(&jupiter_transaction.base_raw_amount).unwrap_or_else(|_| BigInt::zero());
+        let amount_out = BigInt::try_from(&jupiter_transaction.quote_raw_amount).unwrap_or_else(|_| BigInt::zero());
+        store.add(ordinal as u64, format!("{}:0", pool_id), amount_in);
+        store.add(ordinal as u64, format!("{}:1", pool_id), amount_out);
+    }
+}
+
+// Mark each trader the first time they use a protocol within a day, keyed by
+// `{day}:{protocol}:{trader}`
+#[substreams::handlers::store]
+pub fn store_daily_unique_users(clock: Clock, transactions: JupiterTransactions, store: StoreSetIfNotExistsInt64) {
+    if let Some(previous_day) = block_day(&clock).checked_sub(1) {
+        store.delete_prefix(0, &format!("{}:", previous_day));
+    }
+
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        if jupiter_transaction.trader.is_empty() {
+            continue;
+        }
+
+        let protocol = bs58::encode(&jupiter_transaction.program_id).into_string();
+        store.set_if_not_exists(ordinal as u64,
+            format!("{}:{}:{}", day(jupiter_transaction.timestamp), protocol, jupiter_transaction.trader), &1);
+    }
+}
+
+// Daily counters of every protocol, keyed by `{day}:{protocol}:{counter}`
+#[substreams::handlers::store]
+pub fn store_daily_usage(
+    clock: Clock,
+    transactions: JupiterTransactions,
+    daily_unique_users: Deltas<DeltaInt64>,
+    store: StoreAddInt64,
+) {
+    if let Some(previous_day) = block_day(&clock).checked_sub(1) {
+        store.delete_prefix(0, &format!("{}:", previous_day));
+    }
+
+    for delta in daily_unique_users.deltas.iter().filter(|d| d.operation == Operation::Create) {
+        let day = substreams::key::segment_at(&delta.key, 0);
+        let protocol = substreams::key::segment_at(&delta.key, 1);
+        store.add(delta.ordinal, format!("{}:{}:active_users", day, protocol), 1);
+    }
+
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        let counter = match &jupiter_transaction.liquidity_change {
+            Some(change) if change.kind() == Kind::Deposit => "deposits",
+            Some(_) => "withdraws",
+            None if pool_id(jupiter_transaction).is_some() => "swaps",
+            None => continue,
+        };
+
+        let prefix = format!("{}:{}", day(jupiter_transaction.timestamp), bs58::encode(&jupiter_transaction.program_id).into_string());
+        store.add(ordinal as u64, format!("{}:transactions", prefix), 1);
+        store.add(ordinal as u64, format!("{}:{}", prefix, counter), 1);
+    }
+}
+
+// Current usage snapshot of every protocol active in the block, keyed by
+// `{protocol}-{day}`. A snapshot keeps being overwritten during its day and
+// is final once the first block of the next day is reached.
+#[substreams::handlers::store]
+pub fn store_usage_snapshots(
+    clock: Clock,
+    transactions: JupiterTransactions,
+    daily_usage: StoreGetInt64,
+    unique_users: StoreGetInt64,
+    pool_counts: StoreGetInt64,
+    store: StoreSetProto<UsageSnapshot>,
+) {
+    let timestamp = clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds);
+    let active: BTreeSet<(u64, String)> = transactions.transactions.iter()
+        .map(|t| (day(t.timestamp), bs58::encode(&t.program_id).into_string()))
+        .collect();
+
+    for (ordinal, (day, protocol)) in active.into_iter().enumerate() {
+        let daily = |counter: &str| daily_usage.get_last(format!("{}:{}:{}", day, protocol, counter)).unwrap_or(0);
+        let snapshot = UsageSnapshot {
+            protocol: protocol.clone(),
+            day,
+            daily_active_users: daily("active_users"),
+            cumulative_unique_users: unique_users.get_last(&protocol).unwrap_or(0),
+            daily_transaction_count: daily("transactions"),
+            daily_deposit_count: daily("deposits"),
+            daily_withdraw_count: daily("withdraws"),
+            daily_swap_count: daily("swaps"),
+            total_pool_count: pool_counts.get_last(&protocol).unwrap_or(0),
+            block_number: clock.number,
+            timestamp,
+        };
+
+        store.set(ordinal as u64, format!("{}-{}", protocol, day), &snapshot);
+    }
+}
+
+// Accumulate the traded native-unit amounts of every pool per day, keyed by
+// `{day}:{pool}:{token index}`
+#[substreams::handlers::store]
+pub fn store_pool_daily_volumes(clock: Clock, transactions: JupiterTransactions, store: StoreAddBigInt) {
+    if let Some(previous_day) = block_day(&clock).checked_sub(1) {
+        store.delete_prefix(0, &format!("{}:", previous_day));
+    }
+
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        let Some(pool_id) = pool_id(jupiter_transaction) else {
+            continue;
+        };
+
+        let day = day(jupiter_transaction.timestamp);
+        let amount_in = BigInt::try_from(&jupiter_transaction.base_raw_amount).unwrap_or_else(|_| BigInt::zero());
+        let amount_out = BigInt::try_from(&jupiter_transaction.quote_raw_amount).unwrap_or_else(|_| BigInt::zero());
+        store.add(ordinal as u64, format!("{}:{}:0", day, pool_id), amount_in);
+        store.add(ordinal as u64, format!("{}:{}:1", day, pool_id), amount_out);
+    }
+}
+
+// Mark every limit order the first time it is created, filled or cancelled,
+// keyed by order
+#[substreams::handlers::store]
+pub fn store_limit_orders(transactions: JupiterTransactions, store: StoreSetIfNotExistsInt64) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        for event in jupiter_transaction.limit_orders.iter() {
+            store.set_if_not_exists(ordinal as u64, &event.order, &1);
+        }
+    }
+}
+
+// Accumulate the fills of every limit order, keyed by `{order}:making`,
+// `{order}:taking` for the native-unit amounts and `{order}:fills`
+#[substreams::handlers::store]
+pub fn store_limit_order_fills(transactions: JupiterTransactions, store: StoreAddBigInt) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        for event in jupiter_transaction.limit_orders.iter().filter(|e| e.kind() == limit_order_event::Kind::Fill) {
+            let making_amount = BigInt::try_from(&event.making_amount).unwrap_or_else(|_| BigInt::zero());
+            let taking_amount = BigInt::try_from(&event.taking_amount).unwrap_or_else(|_| BigInt::zero());
+            store.add(ordinal as u64, format!("{}:making", event.order), making_amount);
+            store.add(ordinal as u64, format!("{}:taking", event.order), taking_amount);
+            store.add(ordinal as u64, format!("{}:fills", event.order), BigInt::one());
+        }
+    }
+}
+
+// Mark every DCA position the first time it is opened, filled or closed,
+// keyed by DCA account
+#[substreams::handlers::store]
+pub fn store_dca_positions(transactions: JupiterTransactions, store: StoreSetIfNotExistsInt64) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        for event in jupiter_transaction.dca_events.iter() {
+            store.set_if_not_exists(ordinal as u64, &event.dca, &1);
+        }
+    }
+}
+
+// Accumulate the fills of every DCA position, keyed by `{dca}:in`,
+// `{dca}:out` for the native-unit amounts and `{dca}:fills`
+#[substreams::handlers::store]
+pub fn store_dca_fills(transactions: JupiterTransactions, store: StoreAddBigInt) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        for event in jupiter_transaction.dca_events.iter().filter(|e| e.kind() == dca_event::Kind::Filled) {
+            store.add(ordinal as u64, format!("{}:in", event.dca), BigInt::from(event.in_amount));
+            store.add(ordinal as u64, format!("{}:out", event.dca), BigInt::from(event.out_amount));
+            store.add(ordinal as u64, format!("{}:fills", event.dca), BigInt::one());
+        }
+    }
+}
+
+// Mark every Perps position the first time it changes, keyed by position
+#[substreams::handlers::store]
+pub fn store_perp_positions(transactions: JupiterTransactions, store: StoreSetIfNotExistsInt64) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        for event in jupiter_transaction.perp_events.iter() {
+            store.set_if_not_exists(ordinal as u64, &event.position, &1);
+        }
+    }
+}
+
+// Net USD collateral of every Perps position, keyed by `{position}:collateral`.
+// Positions are reused once closed, so the key is dropped when a position is
+// closed or liquidated.
+#[substreams::handlers::store]
+pub fn store_perp_collateral(transactions: JupiterTransactions, store: StoreAddBigInt) {
+    for (ordinal, jupiter_transaction) in transactions.transactions.iter().enumerate() {
+        for event in jupiter_transaction.perp_events.iter() {
+            let key = format!("{}:collateral", event.position);
+            match event.kind() {
+                perp_event::Kind::Increase => store.add(ordinal as u64, &key, BigInt::from(event.collateral_usd_delta)),
+                perp_event::Kind::Decrease if event.position_size_usd > 0 => {
+                    store.add(ordinal as u64, &key, BigInt::from(event.collateral_usd_delta).neg())
+                }
+                _ => store.delete_prefix(ordinal as i64, &format!("{}:", event.position)),
+            }
+        }
+    }
+}
+
+#[substreams::handlers::map]
+pub fn map_jupiter_trades(
+    params: String,
+    clock: Clock,
+    transactions: JupiterTransactions,
+    unique_users: Deltas<DeltaInt64>,
+    pools: Deltas<DeltaProto<Pool>>,
+    pool_counts: Deltas<DeltaInt64>,
+    pool_volumes: StoreGetBigInt,
+    usage_snapshots: Deltas<DeltaProto<UsageSnapshot>>,
+    pool_daily_volumes: StoreGetBigInt,
+    limit_orders: Deltas<DeltaInt64>,
+    limit_order_fills: StoreGetBigInt,
+    dca_positions: Deltas<DeltaInt64>,
+    dca_fills: StoreGetBigInt,
+    perp_positions: Deltas<DeltaInt64>,
+    perp_collateral: StoreGetBigInt,
+) -> Result<EntityChanges, Error> {
+    let params = Params::parse(&params)?;
+    let mut tables = Tables::new();
+
+    // A protocol is first seen with its first user, which creates its row.
+    // Afterwards the row is only updated when one of its counters changes.
+    for delta in unique_users.deltas.iter() {
+        let program_id = &delta.key;
+        if params.logs(LogLevel::Debug) {
+            log::info!("Protocol {} unique users: {} -> {}", program_id, delta.old_value, delta.new_value);
+        }
+
+        if delta.operation == Operation::Create {
+            let protocol = tables.create_row("Protocol", program_id);
+            protocol.set("id", program_id);
+            protocol.set("cumulativeUniqueUsers", delta.new_value);
+            protocol.set("totalPoolCount", 0i64);
+        } else {
+            tables.update_row("Protocol", program_id)
+                .set("cumulativeUniqueUsers", delta.new_value);
+        }
+    }
+
+    for delta in pool_counts.deltas.iter() {
+        if params.logs(LogLevel::Debug) {
+            log::info!("Protocol {} pool count: {} -> {}", delta.key, delta.old_value, delta.new_value);
+        }
+        tables.update_row("Protocol", &delta.key)
+            .set("totalPoolCount", delta.new_value);
+    }
+
+    // Pools are created once, when first registered
+    for delta in pools.deltas.iter().filter(|d| d.operation == Operation::Create) {
+        create_pool(&delta.new_value, &params, &mut tables);
+    }
+
+    for delta in usage_snapshots.deltas.iter() {
+        create_usage_snapshot(&delta.key, &delta.new_value, &params, &mut tables);
+    }
+
+    // Write back the running volume totals of the pools traded in this block
+    let traded_pools: BTreeMap<String, &JupiterTransaction> = transactions.transactions.iter()
+        .filter_map(|t| Some((pool_id(t)?, t)))
+        .collect();
+    for (pool_id, jupiter_transaction) in traded_pools.iter() {
+        let volumes: Vec<BigInt> = (0..2)
+            .map(|token_index| pool_volumes.get_last(format!("{}:{}", pool_id, token_index)).unwrap_or_else(BigInt::zero))
+            .collect();
+        if params.logs(LogLevel::Debug) {
+            log::info!("Pool {} cumulative volume: {} / {}", pool_id, volumes[0], volumes[1]);
+        }
+
+        tables.update_row("LiquidityPool", pool_id)
+            .set("cumulativeVolumeByTokenAmount", volumes);
+
+        create_pool_snapshot(pool_id, jupiter_transaction, &clock, &pool_daily_volumes, &mut tables);
+    }
+
+    // Limit orders seen for the first time, their row is created by their
+    // first event of the block
+    let mut new_orders: BTreeSet<&str> = limit_orders.deltas.iter()
+        .filter(|d| d.operation == Operation::Create)
+        .map(|d| d.key.as_str())
+        .collect();
+
+    // DCA positions seen for the first time, their row is created by their
+    // first event of the block
+    let mut new_dca_positions: BTreeSet<&str> = dca_positions.deltas.iter()
+        .filter(|d| d.operation == Operation::Create)
+        .map(|d| d.key.as_str())
+        .collect();
+
+    // Perps positions seen for the first time, their row is created by
+    // their first event of the block
+    let mut new_positions: BTreeSet<&str> = perp_positions.deltas.iter()
+        .filter(|d| d.operation == Operation::Create)
+        .map(|d| d.key.as_str())
+        .collect();
+
+    for jupiter_transaction in transactions.transactions.iter() {
+        // Hops belong to the swap, instructions without a Swap have none
+        if create_swap(jupiter_transaction, &clock, &params, &mut tables) {
+            for event in jupiter_transaction.swap_events.iter() {
+                create_swap_hop(event, jupiter_transaction, &params, &mut tables);
+            }
+        }
+
+        if let Some(change) = &jupiter_transaction.liquidity_change {
+            create_liquidity_change(change, jupiter_transaction, &clock, &mut tables);
+        }
+
+        for event in jupiter_transaction.limit_orders.iter() {
+            let is_new = new_orders.remove(event.order.as_str());
+            process_limit_order_event(event, is_new, jupiter_transaction, &clock, &limit_order_fills, &params, &mut tables);
+        }
+
+        for event in jupiter_transaction.dca_events.iter() {
+            let is_new = new_dca_positions.remove(event.dca.as_str());
+            process_dca_event(event, is_new, jupiter_transaction, &clock, &dca_fills, &params, &mut tables);
+        }
+
+        for event in jupiter_transaction.perp_events.iter() {
+            let is_new = new_positions.remove(event.position.as_str());
+            process_perp_event(event, is_new, jupiter_transaction, &clock, &perp_collateral, &params, &mut tables);
+        }
+    }
+
     let changes = tables.to_entity_changes();
-    log::info!("Generated {} entity changes", changes.entity_changes.len());
-    
-    // Group changes by entity type
-    let mut protocols = 0;
-    let mut pools = 0;
-    let mut swaps = 0;
-    
-    for change in &changes.entity_changes {
-        match change.entity.as_str() {
-            "Protocol" => protocols += 1,
-            "LiquidityPool" => pools += 1,
-            "Swap" => swaps += 1,
-            _ => {}
+    if params.logs(LogLevel::Info) {
+        log::info!("Generated {} entity changes", changes.entity_changes.len());
+
+        // Group changes by entity type
+        let mut protocols = 0;
+        let mut pools = 0;
+        let mut swaps = 0;
+        let mut deposits = 0;
+        let mut withdraws = 0;
+        let mut limit_orders = 0;
+        let mut dca_positions = 0;
+        let mut dca_fills = 0;
+        let mut perp_positions = 0;
+        let mut perp_trades = 0;
+        let mut liquidations = 0;
+        let mut hops = 0;
+        let mut route_hops = 0;
+
+        for change in &changes.entity_changes {
+            match change.entity.as_str() {
+                "Protocol" => protocols += 1,
+                "LiquidityPool" => pools += 1,
+                "Swap" => swaps += 1,
+                "Deposit" => deposits += 1,
+                "Withdraw" => withdraws += 1,
+                "LimitOrder" => limit_orders += 1,
+                "DcaPosition" => dca_positions += 1,
+                "DcaFill" => dca_fills += 1,
+                "PerpPosition" => perp_positions += 1,
+                "PerpTrade" => perp_trades += 1,
+                "Liquidation" => liquidations += 1,
+                "SwapHop" => hops += 1,
+                "RouteHop" => route_hops += 1,
+                _ => {}
+            }
         }
+
+        log::info!("Entity change summary:");
+        log::info!("- Protocols: {}", protocols);
+        log::info!("- Pools: {}", pools);
+        log::info!("- Swaps: {}", swaps);
+        log::info!("- Deposits: {}", deposits);
+        log::info!("- Withdraws: {}", withdraws);
+        log::info!("- Limit Orders: {}", limit_orders);
+        log::info!("- DCA Positions: {}", dca_positions);
+        log::info!("- DCA Fills: {}", dca_fills);
+        log::info!("- Perp Positions: {}", perp_positions);
+        log::info!("- Perp Trades: {}", perp_trades);
+        log::info!("- Liquidations: {}", liquidations);
+        log::info!("- Swap Hops: {}", hops);
+        log::info!("- Route Hops: {}", route_hops);
     }
-    
-    log::info!("Entity change summary:");
-    log::info!("- Protocols: {}", protocols);
-    log::info!("- Pools: {}", pools);
-    log::info!("- Swaps: {}", swaps);
-    
+
     Ok(changes)
 }
 
+// Keepers fill DCA cycles and limit orders by routing through the aggregator
+// in the same transaction. The fill already reports that volume, so the
+// aggregator instructions of the transaction are flagged as product fills and
+// not counted as swaps.
+fn flag_product_fills(tx_transactions: &mut [JupiterTransaction], programs: &ProgramSet) {
+    let has_fill = tx_transactions.iter().any(|t| {
+        t.dca_events.iter().any(|e| e.kind() == dca_event::Kind::Filled)
+            || t.limit_orders.iter().any(|e| e.kind() == limit_order_event::Kind::Fill)
+    });
+    if !has_fill {
+        return;
+    }
+
+    // The route is signed by the keeper, the user is counted by the fill
+    for jupiter_transaction in tx_transactions.iter_mut() {
+        jupiter_transaction.product_fill = programs.kind(&jupiter_transaction.program_id)
+            .map_or(false, |kind| !kind.is_product());
+        if jupiter_transaction.product_fill {
+            jupiter_transaction.trader.clear();
+        }
+    }
+}
+
+// How an instruction relates to the Jupiter programs of the set
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InstructionClass {
+    // Jupiter instruction not invoked by another Jupiter instruction, either
+    // top-level or CPI'd by a wallet or router program
+    Outer,
+    // Anchor self-CPI event emitted by the enclosing Jupiter instruction
+    Event,
+    // Downstream CPI of the enclosing Jupiter instruction, e.g. into an AMM
+    // or the token program
+    Cpi,
+    Other,
+}
+
+fn classify_instruction(jupiter_height: Option<u32>, is_jupiter_program: bool, is_event: bool) -> InstructionClass {
+    match (jupiter_height, is_jupiter_program) {
+        (Some(_), true) if is_event => InstructionClass::Event,
+        (Some(_), _) => InstructionClass::Cpi,
+        (None, true) if !is_event => InstructionClass::Outer,
+        _ => InstructionClass::Other,
+    }
+}
+
+fn encode_signature(raw_sig: &[u8]) -> String {
+    if raw_sig.is_empty() {
+        "unknown".to_string()
+    } else {
+        bs58::encode(raw_sig).into_string()
+    }
+}
+
+fn to_swap_event(event: &events::SwapEvent, outer_index: u32, inner_index: u32) -> SwapEvent {
+    SwapEvent {
+        amm: bs58::encode(&event.amm).into_string(),
+        input_mint: bs58::encode(&event.input_mint).into_string(),
+        input_amount: event.input_amount,
+        output_mint: bs58::encode(&event.output_mint).into_string(),
+        output_amount: event.output_amount,
+        instruction_index: outer_index,
+        inner_instruction_index: inner_index,
+    }
+}
+
+fn to_dca_event(event: &dca::Event, outer_index: u32, inner_index: u32) -> DcaEvent {
+    let encode = |key: &[u8; 32]| bs58::encode(key).into_string();
+    let decoded = match event {
+        dca::Event::Opened(opened) => DcaEvent {
+            kind: dca_event::Kind::Opened as i32,
+            dca: encode(&opened.dca_key),
+            user: encode(&opened.user_key),
+            input_mint: encode(&opened.input_mint),
+            output_mint: encode(&opened.output_mint),
+            in_deposited: opened.in_deposited,
+            in_amount_per_cycle: opened.in_amount_per_cycle,
+            cycle_frequency: opened.cycle_frequency,
+            ..Default::default()
+        },
+        dca::Event::Filled(filled) => DcaEvent {
+            kind: dca_event::Kind::Filled as i32,
+            dca: encode(&filled.dca_key),
+            user: encode(&filled.user_key),
+            input_mint: encode(&filled.input_mint),
+            output_mint: encode(&filled.output_mint),
+            in_amount: filled.in_amount,
+            out_amount: filled.out_amount,
+            fee_mint: encode(&filled.fee_mint),
+            fee: filled.fee,
+            ..Default::default()
+        },
+        dca::Event::Closed(closed) => DcaEvent {
+            kind: dca_event::Kind::Closed as i32,
+            dca: encode(&closed.dca_key),
+            user: encode(&closed.user_key),
+            input_mint: encode(&closed.input_mint),
+            output_mint: encode(&closed.output_mint),
+            in_deposited: closed.in_deposited,
+            in_amount_per_cycle: closed.in_amount_per_cycle,
+            cycle_frequency: closed.cycle_frequency,
+            total_in_withdrawn: closed.total_in_withdrawn,
+            total_out_withdrawn: closed.total_out_withdrawn,
+            unfilled_amount: closed.unfilled_amount,
+            user_closed: closed.user_closed,
+            ..Default::default()
+        },
+    };
+
+    DcaEvent {
+        instruction_index: outer_index,
+        inner_instruction_index: inner_index,
+        ..decoded
+    }
+}
+
+fn to_perp_event(event: &perps::Event, outer_index: u32, inner_index: u32) -> PerpEvent {
+    let encode = |key: &[u8; 32]| bs58::encode(key).into_string();
+    let decoded = match event {
+        perps::Event::Increase(increase) => PerpEvent {
+            kind: perp_event::Kind::Increase as i32,
+            position: encode(&increase.position_key),
+            owner: encode(&increase.owner),
+            custody: encode(&increase.position_custody),
+            collateral_custody: encode(&increase.position_collateral_custody),
+            side: increase.position_side as u32,
+            position_size_usd: increase.position_size_usd,
+            size_usd_delta: increase.size_usd_delta,
+            collateral_usd_delta: increase.collateral_usd_delta,
+            price: increase.price,
+            fee_usd: increase.fee_usd,
+            ..Default::default()
+        },
+        perps::Event::Decrease(decrease) => PerpEvent {
+            kind: perp_event::Kind::Decrease as i32,
+            position: encode(&decrease.position_key),
+            owner: encode(&decrease.owner),
+            custody: encode(&decrease.position_custody),
+            collateral_custody: encode(&decrease.position_collateral_custody),
+            side: decrease.position_side as u32,
+            position_size_usd: decrease.position_size_usd,
+            size_usd_delta: decrease.size_usd_delta,
+            collateral_usd_delta: decrease.collateral_usd_delta,
+            price: decrease.price,
+            has_profit: decrease.has_profit,
+            pnl_delta: decrease.pnl_delta,
+            fee_usd: decrease.fee_usd,
+            ..Default::default()
+        },
+        // Instant trades are recorded like executed requests
+        perps::Event::InstantIncrease(increase) => PerpEvent {
+            kind: perp_event::Kind::Increase as i32,
+            position: encode(&increase.position_key),
+            owner: encode(&increase.owner),
+            custody: encode(&increase.position_custody),
+            collateral_custody: encode(&increase.position_collateral_custody),
+            side: increase.position_side as u32,
+            position_size_usd: increase.position_size_usd,
+            size_usd_delta: increase.size_usd_delta,
+            collateral_usd_delta: increase.collateral_usd_delta,
+            price: increase.price,
+            fee_usd: increase.fee_usd,
+            ..Default::default()
+        },
+        perps::Event::InstantDecrease(decrease) => PerpEvent {
+            kind: perp_event::Kind::Decrease as i32,
+            position: encode(&decrease.position_key),
+            owner: encode(&decrease.owner),
+            custody: encode(&decrease.position_custody),
+            collateral_custody: encode(&decrease.position_collateral_custody),
+            side: decrease.position_side as u32,
+            position_size_usd: decrease.position_size_usd,
+            size_usd_delta: decrease.size_usd_delta,
+            collateral_usd_delta: decrease.collateral_usd_delta,
+            price: decrease.price,
+            has_profit: decrease.has_profit,
+            pnl_delta: decrease.pnl_delta,
+            fee_usd: decrease.fee_usd,
+            ..Default::default()
+        },
+        perps::Event::Liquidation(liquidation) => PerpEvent {
+            kind: perp_event::Kind::Liquidation as i32,
+            position: encode(&liquidation.position_key),
+            custody: encode(&liquidation.position_custody),
+            collateral_custody: encode(&liquidation.position_collateral_custody),
+            side: liquidation.position_side as u32,
+            position_size_usd: liquidation.position_size_usd,
+            price: liquidation.price,
+            has_profit: liquidation.has_profit,
+            pnl_delta: liquidation.pnl_delta,
+            collateral_mint: encode(&liquidation.position_collateral_mint),
+            transfer_amount_token: liquidation.transfer_amount_token,
+            fee_usd: liquidation.fee_usd,
+            liquidation_fee_usd: liquidation.liquidation_fee_usd,
+            ..Default::default()
+        },
+    };
+
+    PerpEvent {
+        instruction_index: outer_index,
+        inner_instruction_index: inner_index,
+        ..decoded
+    }
+}
+
+fn to_token_balance_change(change: &TokenAccountChange) -> TokenBalanceChange {
+    let amount = change.normalized_delta().to_string().parse::<f64>().unwrap_or(0.0);
+    if change.is_spent() {
+        TokenBalanceChange {
+            mint: change.mint.clone(),
+            amount: -amount,
+            raw_amount: format!("-{}", change.delta()),
+            decimals: change.decimals,
+        }
+    } else {
+        TokenBalanceChange {
+            mint: change.mint.clone(),
+            amount,
+            raw_amount: change.delta().to_string(),
+            decimals: change.decimals,
+        }
+    }
+}
+
+// Signed lamport change of an account, the fee is included for the fee payer
+fn lamports_change(meta: &TransactionStatusMeta, account_index: usize) -> i64 {
+    match (meta.pre_balances.get(account_index), meta.post_balances.get(account_index)) {
+        (Some(&pre), Some(&post)) => post as i64 - pre as i64,
+        _ => 0,
+    }
+}
+
+// Per-transaction state shared by its Jupiter instructions
+struct TransactionContext<'a> {
+    tx_index: usize,
+    accounts: ResolvedAccounts<'a>,
+    is_traced: bool,
+}
+
 fn process_jupiter_instruction(
-    tx: &substreams_solana::pb::sf::solana::r#type::v1::ConfirmedTransaction,
-    transaction: &substreams_solana::pb::sf::solana::r#type::v1::Transaction,
-    message: &substreams_solana::pb::sf::solana::r#type::v1::Message,
-    program_id_str: &str,
-    tables: &mut Tables,
+    instruction_view: &InstructionView,
+    context: &TransactionContext,
+    outer_index: u32,
+    inner_index: Option<u32>,
     block: &Block,
-) -> Result<(), Error> {
+    params: &Params,
+) -> JupiterTransaction {
+    let transaction = instruction_view.transaction();
+    let is_traced = context.is_traced;
+    let meta = instruction_view.meta();
+    let program_id_str = instruction_view.program_id().to_string();
+
     // Use safer transaction ID handling
     let tx_id = if !transaction.signatures.is_empty() {
         bs58::encode(&transaction.signatures[0]).into_string()
@@ -271,129 +958,834 @@ fn process_jupiter_instruction(
         "unknown".to_string()
     };
     
-    let swap_id = format!("swap-{}", tx_id);
-    
-    log::info!("Processing Jupiter transaction {} (checking if matches target: {})", swap_id, tx_id == TARGET_TX_STR);
-    
     // Log which Jupiter program was found
-    let program_type = get_jupiter_program_type(program_id_str);
-    
-    log::info!("Found Jupiter program: {} (Type: {})", program_id_str, program_type);
+    let program_kind = params.programs.kind(instruction_view.program_id().0);
+    let program_type = params.programs.label(instruction_view.program_id().0);
+
+    // Decode the Jupiter v6 instruction and its args, if any
+    let jupiter_instruction = if program_kind == Some(ProgramKind::AggregatorV6) {
+        JupiterInstruction::decode(instruction_view.data())
+    } else {
+        None
+    };
+
+    if params.logs(LogLevel::Info) {
+        log::info!("Processing Jupiter transaction {} (traced: {})", tx_id, is_traced);
+        log::info!("Found Jupiter program: {} (Type: {})", program_id_str, program_type);
+        log::info!("Found Jupiter instruction: {}",
+            jupiter_instruction.as_ref().map_or("Unknown", |i| i.name()));
+    }
+
+    let signer_key = context.accounts.signer();
+    let signer = bs58::encode(signer_key).into_string();
+    let outer_instruction = instruction_view.compiled_instruction();
+
+    // The user transfer authority signs for the source tokens, the fee payer
+    // may be a relayer. Instructions without a known layout fall back to it.
+    let accounts = instruction_view.accounts();
+    let layout = jupiter_instruction.as_ref().and_then(|i| i.account_layout());
+    let trader_key = layout
+        .and_then(|l| accounts.get(l.user_transfer_authority))
+        .map_or(signer_key, |account| account.0.as_slice());
+    let trader = bs58::encode(trader_key).into_string();
+    let trader_index = context.accounts.position(trader_key).unwrap_or(0);
+
+    // Token account the output is sent to, the optional
+    // destination_token_account is set to the program id when omitted
+    let destination_index = layout
+        .and_then(|l| {
+            let destination = accounts.get(l.destination_token_account)?;
+            if destination.0 == instruction_view.program_id().0 {
+                accounts.get(l.user_destination_token_account?)
+            } else {
+                Some(destination)
+            }
+        })
+        .and_then(|destination| context.accounts.position(destination.0));
+
+    let mut jupiter_transaction = JupiterTransaction {
+        slot: block.slot,
+        timestamp: block.block_time.as_ref().map_or(0, |bt| bt.timestamp as u64),
+        signature: transaction.signatures.first().cloned().unwrap_or_default(),
+        program_id: instruction_view.program_id().0.clone(),
+        account_keys: instruction_view.accounts().iter().map(|account| account.0.clone()).collect(),
+        tx_id: tx_id.clone(),
+        tx_index: context.tx_index as i64,
+        signer: signer.clone(),
+        trader: trader.clone(),
+        is_inner_instruction: !instruction_view.is_root(),
+        instruction_index: outer_index,
+        inner_instruction_index: inner_index.unwrap_or(0),
+        instruction_type: jupiter_instruction.as_ref().map_or("", |i| i.name()).to_string(),
+        outer_program: outer_instruction.program_id().to_string(),
+        inner_program: if instruction_view.is_root() { String::new() } else { program_id_str.clone() },
+        txn_fee_lamports: meta.fee,
+        signer_lamports_change: lamports_change(meta, 0),
+        trader_lamports_change: lamports_change(meta, trader_index),
+        outer_executing_accounts: outer_instruction.accounts().iter().map(|account| account.to_string()).collect(),
+        ..Default::default()
+    };
+
+    if let Some(instruction) = &jupiter_instruction {
+        jupiter_transaction.quote = instruction.quote().map(|quote| SwapQuote {
+            exact_out: quote.exact_out,
+            in_amount: quote.in_amount,
+            out_amount: quote.out_amount,
+            slippage_bps: quote.slippage_bps as u32,
+            platform_fee_bps: quote.platform_fee_bps as u32,
+        });
+        jupiter_transaction.route_plan = instruction.route_plan().iter()
+            .map(|step| RouteStep {
+                amm: step.swap.label().to_string(),
+                percent: step.percent as u32,
+                input_index: step.input_index as u32,
+                output_index: step.output_index as u32,
+            })
+            .collect();
+    }
     
-    // Create pool entity
-    if let Some(meta) = &tx.meta {
+    // Per-account token balance deltas of the transaction, plus the native
+    // SOL change of the signer and trader
+    let mut balance_changes = BalanceChanges::from_meta(meta);
+    balance_changes.add_native_balance(meta, 0, &signer);
+    if trader_index != 0 {
+        balance_changes.add_native_balance(meta, trader_index as u32, &trader);
+    }
+
+    // Owner of the destination token account, the trader when the account
+    // is unknown or closed within the transaction (e.g. unwrapped SOL)
+    let recipient = destination_index
+        .and_then(|index| balance_changes.get(index as u32))
+        .map_or(trader.clone(), |change| change.owner.clone());
+    jupiter_transaction.recipient = recipient.clone();
+    if is_traced {
         log::info!("Found {} post token balances for tx {}", meta.post_token_balances.len(), tx_id);
-        
-        // Track all token balance changes
-        let mut token_changes = Vec::new();
-        
-        // Track tokens that decreased (tokens spent)
-        for pre_balance in meta.pre_token_balances.iter() {
-            // Skip entries with empty mint addresses
-            if pre_balance.mint.is_empty() {
-                continue;
+        for change in balance_changes.iter().filter(|c| c.delta() > 0) {
+            log::info!("Token account {} ({} owned by {}): {} -> {}",
+                change.account_index, change.mint, change.owner, change.pre_amount, change.post_amount);
+        }
+    }
+
+    jupiter_transaction.trader_token_balance_changes = balance_changes.by_owner(&trader)
+        .filter(|c| !c.is_native && c.delta() > 0)
+        .map(to_token_balance_change)
+        .collect();
+
+    if let Some(kind) = program_kind.filter(|kind| kind.is_product()) {
+        let instruction_accounts = InstructionAccounts {
+            accounts,
+            resolved: &context.accounts,
+            balances: &balance_changes,
+        };
+        process_product_instruction(kind, instruction_view.data(), &instruction_accounts, params, &mut jupiter_transaction);
+        return jupiter_transaction;
+    }
+
+    // Prefer the balance changes of the trader and recipient, as the pool
+    // vaults of the route also show up in the token balances
+    let spent = balance_changes.spent_by(&trader)
+        .or_else(|| balance_changes.spent().next());
+    let received = balance_changes.received_by(&recipient)
+        .or_else(|| balance_changes.received().next());
+
+    // Find token in/out from changes
+    if let (Some(spent), Some(received)) = (spent, received) {
+        if params.logs(LogLevel::Info) {
+            log::info!("Swap details:");
+            log::info!("- Token In : {} ({} -> {}, change: {})", 
+                spent.mint, spent.pre_amount, spent.post_amount, spent.delta());
+            log::info!("- Token Out: {} ({} -> {}, change: {})", 
+                received.mint, received.pre_amount, received.post_amount, received.delta());
+        }
+
+        jupiter_transaction.base_mint = spent.mint.clone();
+        jupiter_transaction.base_amount = to_token_balance_change(spent).amount.abs();
+        jupiter_transaction.base_raw_amount = spent.delta().to_string();
+        jupiter_transaction.base_decimals = spent.decimals;
+        jupiter_transaction.quote_mint = received.mint.clone();
+        jupiter_transaction.quote_amount = to_token_balance_change(received).amount;
+        jupiter_transaction.quote_raw_amount = received.delta().to_string();
+        jupiter_transaction.quote_decimals = received.decimals;
+    }
+
+    jupiter_transaction
+}
+
+// Decodes a DCA, Limit Order or Perps instruction, and the tokens it moved
+// in or out of a position. The owner of the position is the trader.
+fn process_product_instruction(
+    program_kind: ProgramKind,
+    data: &[u8],
+    accounts: &InstructionAccounts,
+    params: &Params,
+    jupiter_transaction: &mut JupiterTransaction,
+) {
+    let (name, liquidity_change) = match program_kind {
+        ProgramKind::Dca => {
+            let Some(instruction) = DcaInstruction::decode(data) else {
+                return;
+            };
+            // Flash fills are sent by a keeper, the Filled event of
+            // fulfillFlashFill attributes the cycle to the position's user
+            if matches!(instruction, DcaInstruction::InitiateFlashFill | DcaInstruction::FulfillFlashFill { .. }) {
+                jupiter_transaction.trader.clear();
             }
-            
-            let mint = bs58::encode(&pre_balance.mint).into_string();
-            
-            let pre_amount = pre_balance.ui_token_amount.as_ref()
-                .map(|a| a.ui_amount)
-                .unwrap_or(0.0);
-                
-            let post_amount = meta.post_token_balances.iter()
-                .find(|b| b.mint == pre_balance.mint && b.owner == pre_balance.owner)
-                .and_then(|b| b.ui_token_amount.as_ref())
-                .map(|a| a.ui_amount)
-                .unwrap_or(0.0);
-                
-            if pre_amount > post_amount {
-                log::info!("Found spent token: {} ({} -> {})", mint, pre_amount, post_amount);
-                token_changes.push((mint, pre_amount, post_amount, true));
+            (instruction.name(), instruction.liquidity_change(accounts))
+        }
+        ProgramKind::LimitOrder => {
+            let Some(instruction) = LimitOrderInstruction::decode(data) else {
+                return;
+            };
+            // Fills are sent by a keeper, the order belongs to its maker.
+            // preFlashFillOrder names no maker and counts towards no user.
+            if matches!(instruction, LimitOrderInstruction::FillOrder(_)
+                | LimitOrderInstruction::PreFlashFillOrder { .. }
+                | LimitOrderInstruction::FlashFillOrder { .. }) {
+                jupiter_transaction.trader.clear();
             }
+            if let Some(event) = instruction.order_event(accounts) {
+                jupiter_transaction.trader = event.maker.clone();
+                jupiter_transaction.limit_orders.push(event);
+            }
+            (instruction.name(), instruction.liquidity_change(accounts))
         }
-        
-        // Track tokens that increased (tokens received)
-        for post_balance in meta.post_token_balances.iter() {
-            // Skip entries with empty mint addresses
-            if post_balance.mint.is_empty() {
-                continue;
+        ProgramKind::Perps => {
+            let Some(instruction) = PerpsInstruction::decode(data) else {
+                return;
+            };
+            // Liquidations are sent by a keeper and their event carries no
+            // owner, so they count towards no user
+            if instruction == PerpsInstruction::LiquidateFullPosition4 {
+                jupiter_transaction.trader.clear();
             }
-            
-            let mint = bs58::encode(&post_balance.mint).into_string();
-            
-            let post_amount = post_balance.ui_token_amount.as_ref()
-                .map(|a| a.ui_amount)
-                .unwrap_or(0.0);
-                
-            let pre_amount = meta.pre_token_balances.iter()
-                .find(|b| b.mint == post_balance.mint && b.owner == post_balance.owner)
-                .and_then(|b| b.ui_token_amount.as_ref())
-                .map(|a| a.ui_amount)
-                .unwrap_or(0.0);
-                
-            if post_amount > pre_amount {
-                log::info!("Found received token: {} ({} -> {})", mint, pre_amount, post_amount);
-                token_changes.push((mint, pre_amount, post_amount, false));
+            (instruction.name(), instruction.liquidity_change(accounts))
+        }
+        ProgramKind::AggregatorV6 | ProgramKind::AggregatorV4 => return,
+    };
+
+    if params.logs(LogLevel::Debug) {
+        log::info!("Found {:?} instruction: {}", program_kind, name);
+    }
+    jupiter_transaction.instruction_type = name.to_string();
+
+    if let Some(change) = liquidity_change {
+        if params.logs(LogLevel::Debug) {
+            log::info!("{:?} of {:?} {:?} into {} by {}",
+                change.kind(), change.input_token_amounts, change.input_tokens, change.pool, change.owner);
+        }
+        jupiter_transaction.trader = change.owner.clone();
+        jupiter_transaction.liquidity_change = Some(change);
+    }
+}
+
+// {prefix}-{signature}-{outer index}, with the inner index appended for
+// Jupiter instructions CPI'd by another program
+fn instruction_id(prefix: &str, jupiter_transaction: &JupiterTransaction) -> String {
+    if jupiter_transaction.is_inner_instruction {
+        format!("{}-{}-{}-{}", prefix, jupiter_transaction.tx_id, jupiter_transaction.instruction_index, jupiter_transaction.inner_instruction_index)
+    } else {
+        format!("{}-{}-{}", prefix, jupiter_transaction.tx_id, jupiter_transaction.instruction_index)
+    }
+}
+
+fn create_swap_hop(event: &SwapEvent, jupiter_transaction: &JupiterTransaction, params: &Params, tables: &mut Tables) {
+    let hop_id = format!("hop-{}-{}-{}", jupiter_transaction.tx_id, event.instruction_index, event.inner_instruction_index);
+
+    if params.logs(LogLevel::Debug) {
+        log::info!("Found Jupiter swap event {}:", hop_id);
+        log::info!("- AMM: {}", event.amm);
+        log::info!("- Input : {} {}", event.input_amount, event.input_mint);
+        log::info!("- Output: {} {}", event.output_amount, event.output_mint);
+    }
+
+    let hop = tables.create_row("SwapHop", &hop_id);
+    hop.set("id", &hop_id);
+    hop.set("swap", instruction_id("swap", jupiter_transaction));
+    hop.set("amm", &event.amm);
+    hop.set("inputMint", &event.input_mint);
+    hop.set("inputAmount", BigInt::from(event.input_amount));
+    hop.set("outputMint", &event.output_mint);
+    hop.set("outputAmount", BigInt::from(event.output_amount));
+    hop.set("outerInstructionIndex", event.instruction_index as i64);
+    hop.set("innerInstructionIndex", event.inner_instruction_index as i64);
+    hop.set("slot", jupiter_transaction.slot as i64);
+    hop.set("timestamp", jupiter_transaction.timestamp as i64);
+}
+
+// {program id}-{token in}-{token out}, None unless the instruction both
+// spent and received a token, and did not route a product fill
+fn pool_id(jupiter_transaction: &JupiterTransaction) -> Option<String> {
+    if jupiter_transaction.product_fill || jupiter_transaction.base_mint.is_empty() || jupiter_transaction.quote_mint.is_empty() {
+        return None;
+    }
+
+    Some(format!("{}-{}-{}",
+        bs58::encode(&jupiter_transaction.program_id).into_string(),
+        jupiter_transaction.base_mint,
+        jupiter_transaction.quote_mint))
+}
+
+// Days since Unix epoch of a timestamp in seconds
+fn day(timestamp: u64) -> u64 {
+    timestamp / SECONDS_PER_DAY
+}
+
+// Days since Unix epoch of the block. Day-keyed stores drop the keys of the
+// previous day, which are no longer read once the block's day has started.
+fn block_day(clock: &Clock) -> u64 {
+    day(clock.timestamp.as_ref().map_or(0, |ts| ts.seconds as u64))
+}
+
+fn create_usage_snapshot(snapshot_id: &str, snapshot: &UsageSnapshot, params: &Params, tables: &mut Tables) {
+    if params.logs(LogLevel::Debug) {
+        log::info!("Usage snapshot {}: {} active users, {} swaps",
+            snapshot_id, snapshot.daily_active_users, snapshot.daily_swap_count);
+    }
+
+    let row = tables.create_row("UsageMetricsDailySnapshot", snapshot_id);
+    row.set("id", snapshot_id);
+    row.set("protocol", &snapshot.protocol);
+    row.set("dailyActiveUsers", snapshot.daily_active_users);
+    row.set("cumulativeUniqueUsers", snapshot.cumulative_unique_users);
+    row.set("dailyTransactionCount", snapshot.daily_transaction_count);
+    row.set("dailyDepositCount", snapshot.daily_deposit_count);
+    row.set("dailyWithdrawCount", snapshot.daily_withdraw_count);
+    row.set("dailySwapCount", snapshot.daily_swap_count);
+    row.set("totalPoolCount", snapshot.total_pool_count);
+    row.set("blockNumber", snapshot.block_number as i64);
+    row.set("timestamp", snapshot.timestamp);
+}
+
+// Snapshot of a pool for the day of the block, rewritten on every trade so
+// the last block of the day leaves the end-of-day values
+fn create_pool_snapshot(
+    pool_id: &str,
+    jupiter_transaction: &JupiterTransaction,
+    clock: &Clock,
+    pool_daily_volumes: &StoreGetBigInt,
+    tables: &mut Tables,
+) {
+    let day = day(jupiter_transaction.timestamp);
+    let snapshot_id = format!("{}-{}", pool_id, day);
+    let daily_volume = |token_index: u32| {
+        pool_daily_volumes.get_last(format!("{}:{}:{}", day, pool_id, token_index)).unwrap_or_else(BigInt::zero)
+    };
+
+    let row = tables.create_row("LiquidityPoolDailySnapshot", &snapshot_id);
+    row.set("id", &snapshot_id);
+    row.set("protocol", bs58::encode(&jupiter_transaction.program_id).into_string());
+    row.set("pool", pool_id);
+    row.set("blockNumber", clock.number as i64);
+    row.set("timestamp", clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds));
+    row.set("dailyVolumeByToken0Amount", daily_volume(0));
+    row.set("dailyVolumeByToken1Amount", daily_volume(1));
+    // Jupiter routes through other AMMs and holds no reserves or output token
+    // of its own, so the balances and output token supply are left unset.
+}
+
+fn create_pool(pool: &Pool, params: &Params, tables: &mut Tables) {
+    if params.logs(LogLevel::Debug) {
+        log::info!("Creating pool entity {} for tokens {:?} (slot: {})",
+            pool.id, pool.input_tokens, pool.created_slot);
+    }
+
+    let row = tables.create_row("LiquidityPool", &pool.id);
+    row.set("id", &pool.id);
+    row.set("protocol", &pool.protocol);
+    row.set("inputTokens", pool.input_tokens.clone());
+    // Balances and output token supply stay null, as on the snapshots
+    row.set("cumulativeVolumeByTokenAmount", vec![BigInt::zero(), BigInt::zero()]);
+    row.set("createdTimestamp", pool.created_timestamp as i64);
+    row.set("createdBlockNumber", pool.created_slot as i64);
+}
+
+// Creates the Swap row and its RouteHops, returns false if the instruction
+// is not a swap
+fn create_swap(jupiter_transaction: &JupiterTransaction, clock: &Clock, params: &Params, tables: &mut Tables) -> bool {
+    // Only instructions with both a spent and a received side are swaps
+    let Some(pool_id) = pool_id(jupiter_transaction) else {
+        return false;
+    };
+
+    let program_id_str = bs58::encode(&jupiter_transaction.program_id).into_string();
+    let swap_id = instruction_id("swap", jupiter_transaction);
+    let token_in = &jupiter_transaction.base_mint;
+    let token_out = &jupiter_transaction.quote_mint;
+    let timestamp = clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds);
+
+    // Create swap entity
+    let swap = tables.create_row("Swap", &swap_id);
+    swap.set("id", &swap_id);
+    swap.set("outerInstructionIndex", jupiter_transaction.instruction_index as i64);
+    if jupiter_transaction.is_inner_instruction {
+        swap.set("innerInstructionIndex", jupiter_transaction.inner_instruction_index as i64);
+    }
+    swap.set("blockHash", &clock.id);
+    swap.set("protocol", &program_id_str);
+    swap.set("pool", &pool_id);
+    swap.set("from", &jupiter_transaction.trader);
+    swap.set("to", &jupiter_transaction.recipient);
+    swap.set("slot", clock.number as i64);
+    swap.set("blockNumber", clock.number as i64);
+    swap.set("timestamp", timestamp);
+    swap.set("tokenIn", token_in);
+    swap.set("tokenOut", token_out);
+    
+    // Set exact native-unit amounts, plus their decimal-normalized values
+    let amount_in = BigInt::try_from(&jupiter_transaction.base_raw_amount).unwrap_or_else(|_| BigInt::zero());
+    let amount_out = BigInt::try_from(&jupiter_transaction.quote_raw_amount).unwrap_or_else(|_| BigInt::zero());
+    swap.set("amountInDecimal", amount_in.to_decimal(jupiter_transaction.base_decimals as u64));
+    swap.set("amountOutDecimal", amount_out.to_decimal(jupiter_transaction.quote_decimals as u64));
+    swap.set("amountIn", amount_in);
+    swap.set("amountOut", amount_out);
+    swap.set("tokenInDecimals", jupiter_transaction.base_decimals as i32);
+    swap.set("tokenOutDecimals", jupiter_transaction.quote_decimals as i32);
+
+    // Attach the decoded instruction args
+    if !jupiter_transaction.instruction_type.is_empty() {
+        swap.set("instructionType", &jupiter_transaction.instruction_type);
+    }
+
+    if let Some(quote) = &jupiter_transaction.quote {
+        if let Some(in_amount) = quote.in_amount {
+            swap.set("quotedAmountIn", BigInt::from(in_amount));
+        }
+        swap.set("quotedAmountOut", BigInt::from(quote.out_amount));
+        swap.set("exactOut", quote.exact_out);
+        swap.set("slippageBps", quote.slippage_bps as i32);
+        swap.set("platformFeeBps", quote.platform_fee_bps as i32);
+    }
+
+    // One RouteHop per planned step, split routes share input/output indexes
+    for (step_index, step) in jupiter_transaction.route_plan.iter().enumerate() {
+        let hop_id = format!("{}-step-{}", swap_id, step_index);
+        if params.logs(LogLevel::Debug) {
+            log::info!("Route step {}: {} ({}%, {} -> {})",
+                step_index, step.amm, step.percent, step.input_index, step.output_index);
+        }
+
+        let hop = tables.create_row("RouteHop", &hop_id);
+        hop.set("id", &hop_id);
+        hop.set("swap", &swap_id);
+        hop.set("stepIndex", step_index as i32);
+        hop.set("amm", &step.amm);
+        hop.set("percent", step.percent as i32);
+        hop.set("inputIndex", step.input_index as i32);
+        hop.set("outputIndex", step.output_index as i32);
+    }
+
+    true
+}
+
+// Deposit or Withdraw row of a liquidity change. Tokens flow from the owner
+// to the position account on deposits, and back on withdraws.
+fn create_liquidity_change(change: &LiquidityChange, jupiter_transaction: &JupiterTransaction, clock: &Clock, tables: &mut Tables) {
+    let (entity, from, to) = match change.kind() {
+        Kind::Deposit => ("Deposit", &change.owner, &change.pool),
+        Kind::Withdraw => ("Withdraw", &change.pool, &change.owner),
+    };
+    let id = instruction_id(&entity.to_lowercase(), jupiter_transaction);
+    let amounts: Vec<BigInt> = change.input_token_amounts.iter()
+        .map(|amount| BigInt::try_from(amount).unwrap_or_else(|_| BigInt::zero()))
+        .collect();
+
+    let row = tables.create_row(entity, &id);
+    row.set("id", &id);
+    row.set("blockHash", &clock.id);
+    row.set("protocol", bs58::encode(&jupiter_transaction.program_id).into_string());
+    row.set("position", &change.pool);
+    row.set("from", from);
+    row.set("to", to);
+    row.set("slot", clock.number as i64);
+    row.set("blockNumber", clock.number as i64);
+    row.set("timestamp", clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds));
+    row.set("inputTokens", change.input_tokens.clone());
+    row.set("outputToken", &change.output_token);
+    row.set("inputTokenAmounts", amounts);
+    row.set("outputTokenAmount", BigInt::try_from(&change.output_token_amount).unwrap_or_else(|_| BigInt::zero()));
+}
+
+// Orders are created by their first event, fills and cancellations update
+// their progress and status. Orders opened before the start block are first
+// seen filled or cancelled, their original amounts and creation are then
+// left unset. Fill totals are read from store_limit_order_fills, so orders
+// filled more than once in a block end up with the totals of the block.
+fn process_limit_order_event(
+    event: &LimitOrderEvent,
+    is_new: bool,
+    jupiter_transaction: &JupiterTransaction,
+    clock: &Clock,
+    limit_order_fills: &StoreGetBigInt,
+    params: &Params,
+    tables: &mut Tables,
+) {
+    let timestamp = clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds);
+    if params.logs(LogLevel::Debug) {
+        log::info!("Limit order {} {:?}: {} -> {} (remaining: {})",
+            event.order, event.kind(), event.making_amount, event.taking_amount, event.remaining_making_amount);
+    }
+
+    let fills = |counter: &str| {
+        limit_order_fills.get_last(format!("{}:{}", event.order, counter)).unwrap_or_else(BigInt::zero)
+    };
+    let remaining = BigInt::try_from(&event.remaining_making_amount).unwrap_or_else(|_| BigInt::zero());
+    let status = match event.kind() {
+        limit_order_event::Kind::Create => "OPEN",
+        limit_order_event::Kind::Fill if remaining.is_zero() => "FILLED",
+        limit_order_event::Kind::Fill => "PARTIALLY_FILLED",
+        limit_order_event::Kind::Cancel => "CANCELLED",
+    };
+
+    if is_new || event.kind() == limit_order_event::Kind::Create {
+        let row = tables.create_row("LimitOrder", &event.order);
+        row.set("id", &event.order);
+        row.set("protocol", bs58::encode(&jupiter_transaction.program_id).into_string());
+        row.set("maker", &event.maker);
+        if !event.input_mint.is_empty() {
+            row.set("inputMint", &event.input_mint);
+        }
+        if !event.output_mint.is_empty() {
+            row.set("outputMint", &event.output_mint);
+        }
+        if event.kind() == limit_order_event::Kind::Create {
+            row.set("makingAmount", BigInt::try_from(&event.making_amount).unwrap_or_else(|_| BigInt::zero()));
+            row.set("takingAmount", BigInt::try_from(&event.taking_amount).unwrap_or_else(|_| BigInt::zero()));
+            if event.expired_at != 0 {
+                row.set("expiredAt", event.expired_at);
             }
+            row.set("createdTx", &jupiter_transaction.tx_id);
+            row.set("createdBlockNumber", clock.number as i64);
+            row.set("createdTimestamp", timestamp);
         }
+        row.set("filledMakingAmount", fills("making"));
+        row.set("filledTakingAmount", fills("taking"));
+        row.set("remainingMakingAmount", remaining);
+        row.set("fillCount", fills("fills"));
+        row.set("status", status);
+        row.set("updatedBlockNumber", clock.number as i64);
+        row.set("updatedTimestamp", timestamp);
+        return;
+    }
 
-        // Find token in/out from changes
-        if let (Some(spent), Some(received)) = (
-            token_changes.iter().find(|(_, _, _, is_spent)| *is_spent),
-            token_changes.iter().find(|(_, _, _, is_spent)| !*is_spent)
-        ) {
-            let (token_in, amount_in_pre, amount_in_post, _) = spent;
-            let (token_out, amount_out_pre, amount_out_post, _) = received;
-            let pool_id = format!("{}-{}-{}", program_id_str, token_in, token_out);
-            
-            log::info!("Creating pool entity {} for tokens {} and {} (tx: {})", 
-                pool_id, token_in, token_out, tx_id);
+    match event.kind() {
+        limit_order_event::Kind::Fill => {
+            tables.update_row("LimitOrder", &event.order)
+                .set("filledMakingAmount", fills("making"))
+                .set("filledTakingAmount", fills("taking"))
+                .set("fillCount", fills("fills"))
+                .set("remainingMakingAmount", remaining)
+                .set("status", status)
+                .set("updatedBlockNumber", clock.number as i64)
+                .set("updatedTimestamp", timestamp);
+        }
+        _ => {
+            tables.update_row("LimitOrder", &event.order)
+                .set("status", status)
+                .set("updatedBlockNumber", clock.number as i64)
+                .set("updatedTimestamp", timestamp);
+        }
+    }
+}
 
-            log::info!("Swap details:");
-            log::info!("- Token In : {} ({} -> {}, change: {})", 
-                token_in, amount_in_pre, amount_in_post, (amount_in_pre - amount_in_post).abs());
-            log::info!("- Token Out: {} ({} -> {}, change: {})", 
-                token_out, amount_out_pre, amount_out_post, (amount_out_post - amount_out_pre).abs());
-            
-            // Create pool entity
-            let pool = tables.create_row("LiquidityPool", &pool_id);
-            pool.set("id", &pool_id);
-            pool.set("protocol", program_id_str);
-            pool.set("inputTokens", vec![token_in.clone(), token_out.clone()]);
-            pool.set("token0Balance", 0i64);
-            pool.set("token1Balance", 0i64);
-            pool.set("outputTokenSupply", 0i64);
-            pool.set("cumulativeVolumeByTokenAmount", vec!["0".to_string(), "0".to_string()]);
-            pool.set("createdTimestamp", block.block_time.as_ref().map_or(0i64, |bt| bt.timestamp));
-            pool.set("createdBlockNumber", block.slot as i64);
-
-            // Create swap entity
-            let swap = tables.create_row("Swap", &swap_id);
-            swap.set("id", &swap_id);
-            swap.set("blockHash", bs58::encode(&block.blockhash).into_string());
-            swap.set("protocol", program_id_str);
-            swap.set("pool", &pool_id);
-            
-            // Add bounds check for account keys and use safer handling
-            let from_key = if !message.account_keys.is_empty() {
-                bs58::encode(&message.account_keys[0]).into_string()
-            } else {
-                "unknown".to_string()
-            };
-            
-            swap.set("from", from_key.clone());
-            swap.set("to", from_key);
-            swap.set("slot", block.slot as i64);
-            swap.set("blockNumber", block.slot as i64);
-            swap.set("timestamp", block.block_time.as_ref().map_or(0i64, |bt| bt.timestamp));
-            swap.set("tokenIn", token_in);
-            swap.set("tokenOut", token_out);
-            
-            // Set amounts based on the changes
-            swap.set("amountIn", (amount_in_pre - amount_in_post).abs().to_string());
-            swap.set("amountOut", (amount_out_post - amount_out_pre).abs().to_string());
+// Positions are created by their first event, every fill adds a DcaFill and
+// updates the position totals, closing records the lifetime totals.
+// Positions opened before the start block are first seen filled or closed,
+// their creation is then left unset. The keeper's aggregator route of a fill
+// is flagged product_fill and creates no Swap, so only the DcaFill reports it.
+fn process_dca_event(
+    event: &DcaEvent,
+    is_new: bool,
+    jupiter_transaction: &JupiterTransaction,
+    clock: &Clock,
+    dca_fills: &StoreGetBigInt,
+    params: &Params,
+    tables: &mut Tables,
+) {
+    let timestamp = clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds);
+    if params.logs(LogLevel::Debug) {
+        log::info!("DCA {} {:?} by {}: {} {} -> {} {}",
+            event.dca, event.kind(), event.user, event.in_amount, event.input_mint, event.out_amount, event.output_mint);
+    }
+
+    let fills = |counter: &str| dca_fills.get_last(format!("{}:{}", event.dca, counter)).unwrap_or_else(BigInt::zero);
+
+    if is_new || event.kind() == dca_event::Kind::Opened {
+        let row = tables.create_row("DcaPosition", &event.dca);
+        row.set("id", &event.dca);
+        row.set("protocol", bs58::encode(&jupiter_transaction.program_id).into_string());
+        row.set("user", &event.user);
+        row.set("inputMint", &event.input_mint);
+        row.set("outputMint", &event.output_mint);
+        // Fill events do not carry the parameters of the position
+        if event.kind() != dca_event::Kind::Filled {
+            row.set("inDeposited", BigInt::from(event.in_deposited));
+            row.set("inAmountPerCycle", BigInt::from(event.in_amount_per_cycle));
+            row.set("cycleFrequency", event.cycle_frequency);
+        }
+        if event.kind() == dca_event::Kind::Opened {
+            row.set("createdTx", &jupiter_transaction.tx_id);
+            row.set("createdBlockNumber", clock.number as i64);
+            row.set("createdTimestamp", timestamp);
+        }
+        row.set("totalInFilled", fills("in"));
+        row.set("totalOutFilled", fills("out"));
+        row.set("fillCount", fills("fills"));
+        row.set("status", "OPEN");
+        row.set("updatedBlockNumber", clock.number as i64);
+        row.set("updatedTimestamp", timestamp);
+    }
+
+    match event.kind() {
+        dca_event::Kind::Opened => {}
+        dca_event::Kind::Filled => {
+            let fill_id = format!("dca-fill-{}-{}-{}", jupiter_transaction.tx_id, event.instruction_index, event.inner_instruction_index);
+            let row = tables.create_row("DcaFill", &fill_id);
+            row.set("id", &fill_id);
+            row.set("position", &event.dca);
+            row.set("keeper", &jupiter_transaction.signer);
+            row.set("inputMint", &event.input_mint);
+            row.set("inAmount", BigInt::from(event.in_amount));
+            row.set("outputMint", &event.output_mint);
+            row.set("outAmount", BigInt::from(event.out_amount));
+            row.set("feeMint", &event.fee_mint);
+            row.set("fee", BigInt::from(event.fee));
+            row.set("slot", jupiter_transaction.slot as i64);
+            row.set("blockNumber", clock.number as i64);
+            row.set("timestamp", timestamp);
+
+            tables.update_row("DcaPosition", &event.dca)
+                .set("totalInFilled", fills("in"))
+                .set("totalOutFilled", fills("out"))
+                .set("fillCount", fills("fills"))
+                .set("updatedBlockNumber", clock.number as i64)
+                .set("updatedTimestamp", timestamp);
+        }
+        dca_event::Kind::Closed => {
+            tables.update_row("DcaPosition", &event.dca)
+                .set("inDeposited", BigInt::from(event.in_deposited))
+                .set("totalInWithdrawn", BigInt::from(event.total_in_withdrawn))
+                .set("totalOutWithdrawn", BigInt::from(event.total_out_withdrawn))
+                .set("unfilledAmount", BigInt::from(event.unfilled_amount))
+                .set("status", if event.user_closed { "CLOSED" } else { "COMPLETED" })
+                .set("updatedBlockNumber", clock.number as i64)
+                .set("updatedTimestamp", timestamp);
+        }
+    }
+}
+
+// Every increase or decrease adds a PerpTrade, every liquidation a
+// Liquidation, and both update the position. Positions are created by their
+// first event, and keep their row when reopened.
+fn process_perp_event(
+    event: &PerpEvent,
+    is_new: bool,
+    jupiter_transaction: &JupiterTransaction,
+    clock: &Clock,
+    perp_collateral: &StoreGetBigInt,
+    params: &Params,
+    tables: &mut Tables,
+) {
+    let timestamp = clock.timestamp.as_ref().map_or(0i64, |ts| ts.seconds);
+    let protocol = bs58::encode(&jupiter_transaction.program_id).into_string();
+    let side = perps::side_name(event.side as u8);
+    if params.logs(LogLevel::Debug) {
+        log::info!("Perp position {} {:?} ({}): size {} (delta {}), collateral delta {}",
+            event.position, event.kind(), side, event.position_size_usd, event.size_usd_delta, event.collateral_usd_delta);
+    }
+
+    match event.kind() {
+        perp_event::Kind::Increase | perp_event::Kind::Decrease => {
+            let action = if event.kind() == perp_event::Kind::Increase { "INCREASE" } else { "DECREASE" };
+            let trade_id = format!("perp-trade-{}-{}-{}", jupiter_transaction.tx_id, event.instruction_index, event.inner_instruction_index);
+            let row = tables.create_row("PerpTrade", &trade_id);
+            row.set("id", &trade_id);
+            row.set("protocol", &protocol);
+            row.set("position", &event.position);
+            row.set("owner", &event.owner);
+            row.set("custody", &event.custody);
+            row.set("collateralCustody", &event.collateral_custody);
+            row.set("side", side);
+            row.set("action", action);
+            row.set("sizeUsdDelta", BigInt::from(event.size_usd_delta));
+            row.set("collateralUsdDelta", BigInt::from(event.collateral_usd_delta));
+            row.set("positionSizeUsd", BigInt::from(event.position_size_usd));
+            row.set("price", BigInt::from(event.price));
+            if event.kind() == perp_event::Kind::Decrease {
+                row.set("hasProfit", event.has_profit);
+                row.set("pnlDelta", BigInt::from(event.pnl_delta));
+            }
+            row.set("instructionType", &jupiter_transaction.instruction_type);
+            row.set("slot", jupiter_transaction.slot as i64);
+            row.set("blockNumber", clock.number as i64);
+            row.set("timestamp", timestamp);
+        }
+        perp_event::Kind::Liquidation => {
+            let liquidation_id = format!("liquidation-{}-{}-{}", jupiter_transaction.tx_id, event.instruction_index, event.inner_instruction_index);
+            let row = tables.create_row("Liquidation", &liquidation_id);
+            row.set("id", &liquidation_id);
+            row.set("protocol", &protocol);
+            row.set("position", &event.position);
+            row.set("liquidator", &jupiter_transaction.signer);
+            row.set("custody", &event.custody);
+            row.set("collateralCustody", &event.collateral_custody);
+            row.set("collateralMint", &event.collateral_mint);
+            row.set("side", side);
+            row.set("sizeUsd", BigInt::from(event.position_size_usd));
+            row.set("collateralAmount", BigInt::from(event.transfer_amount_token));
+            row.set("hasProfit", event.has_profit);
+            row.set("pnlDelta", BigInt::from(event.pnl_delta));
+            row.set("price", BigInt::from(event.price));
+            row.set("feeUsd", BigInt::from(event.fee_usd));
+            row.set("liquidationFeeUsd", BigInt::from(event.liquidation_fee_usd));
+            row.set("slot", jupiter_transaction.slot as i64);
+            row.set("blockNumber", clock.number as i64);
+            row.set("timestamp", timestamp);
+        }
+    }
+
+    let (size_usd, status) = match event.kind() {
+        perp_event::Kind::Liquidation => (0, "LIQUIDATED"),
+        _ if event.position_size_usd == 0 => (0, "CLOSED"),
+        _ => (event.position_size_usd, "OPEN"),
+    };
+
+    let row = if is_new {
+        let row = tables.create_row("PerpPosition", &event.position);
+        row.set("id", &event.position);
+        row.set("protocol", &protocol);
+        row.set("custody", &event.custody);
+        row.set("collateralCustody", &event.collateral_custody);
+        row.set("side", side);
+        row.set("createdBlockNumber", clock.number as i64);
+        row.set("createdTimestamp", timestamp);
+        row
+    } else {
+        tables.update_row("PerpPosition", &event.position)
+    };
+    if !event.owner.is_empty() {
+        row.set("owner", &event.owner);
+    }
+    row.set("sizeUsd", BigInt::from(size_usd));
+    row.set("collateralUsd", perp_collateral.get_last(format!("{}:collateral", event.position)).unwrap_or_else(BigInt::zero));
+    row.set("status", status);
+    row.set("updatedBlockNumber", clock.number as i64);
+    row.set("updatedTimestamp", timestamp);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::decode_pubkey;
+
+    const V6: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+    const LIMIT_ORDER: &str = "jupoNjAxXgZ4rjzxzPMP4oxduvQsQtZzyknqvzYNrNu";
+    const DCA: &str = "DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M";
+
+    fn instruction(program: &str) -> JupiterTransaction {
+        JupiterTransaction {
+            program_id: decode_pubkey(program).unwrap().to_vec(),
+            ..Default::default()
         }
     }
 
-    Ok(())
+    #[test]
+    fn flags_the_route_of_a_dca_fill() {
+        let mut dca = instruction(DCA);
+        dca.dca_events.push(DcaEvent { kind: dca_event::Kind::Filled as i32, ..Default::default() });
+        // initiateFlashFill, v6 route, fulfillFlashFill
+        let mut tx_transactions = vec![instruction(DCA), instruction(V6), dca];
+
+        flag_product_fills(&mut tx_transactions, &ProgramSet::default());
+        let flags: Vec<bool> = tx_transactions.iter().map(|t| t.product_fill).collect();
+        assert_eq!(flags, vec![false, true, false]);
+    }
+
+    #[test]
+    fn flags_the_route_of_a_limit_order_flash_fill() {
+        let mut flash_fill = instruction(LIMIT_ORDER);
+        flash_fill.limit_orders.push(LimitOrderEvent { kind: limit_order_event::Kind::Fill as i32, ..Default::default() });
+        // preFlashFillOrder, v6 route, flashFillOrder
+        let mut tx_transactions = vec![instruction(LIMIT_ORDER), instruction(V6), flash_fill];
+
+        flag_product_fills(&mut tx_transactions, &ProgramSet::default());
+        let flags: Vec<bool> = tx_transactions.iter().map(|t| t.product_fill).collect();
+        assert_eq!(flags, vec![false, true, false]);
+    }
+
+    #[test]
+    fn keeps_swaps_next_to_other_product_events() {
+        let mut order = instruction(LIMIT_ORDER);
+        order.limit_orders.push(LimitOrderEvent { kind: limit_order_event::Kind::Create as i32, ..Default::default() });
+        let mut tx_transactions = vec![instruction(V6), order];
+
+        flag_product_fills(&mut tx_transactions, &ProgramSet::default());
+        assert!(tx_transactions.iter().all(|t| !t.product_fill));
+    }
+
+    #[test]
+    fn keeps_keepers_out_of_the_user_counts() {
+        let resolved = ResolvedAccounts::default();
+        let balances = BalanceChanges::default();
+        let accounts = InstructionAccounts { accounts: vec![], resolved: &resolved, balances: &balances };
+        let params = Params::default();
+
+        let mut initiate = instruction(DCA);
+        initiate.trader = "keeper".to_string();
+        process_product_instruction(ProgramKind::Dca, &dca::INITIATE_FLASH_FILL, &accounts, &params, &mut initiate);
+        assert_eq!(initiate.instruction_type, "initiateFlashFill");
+        assert!(initiate.trader.is_empty());
+
+        let mut pre_flash_fill = instruction(LIMIT_ORDER);
+        pre_flash_fill.trader = "keeper".to_string();
+        let data = events::instruction_data(&limit_order::PRE_FLASH_FILL_ORDER, &[&600u64.to_le_bytes()]);
+        process_product_instruction(ProgramKind::LimitOrder, &data, &accounts, &params, &mut pre_flash_fill);
+        assert!(pre_flash_fill.trader.is_empty());
+
+        let mut fulfill = instruction(DCA);
+        fulfill.dca_events.push(DcaEvent { kind: dca_event::Kind::Filled as i32, ..Default::default() });
+        fulfill.trader = "user".to_string();
+        let mut route = instruction(V6);
+        route.trader = "keeper".to_string();
+        let mut tx_transactions = vec![initiate, route, fulfill];
+
+        flag_product_fills(&mut tx_transactions, &ProgramSet::default());
+        let traders: Vec<&str> = tx_transactions.iter().map(|t| t.trader.as_str()).collect();
+        assert_eq!(traders, vec!["", "", "user"]);
+    }
+
+    #[test]
+    fn creates_swaps_only_for_instructions_with_both_sides() {
+        let params = Params::default();
+        let clock = Clock::default();
+        let mut swap = instruction(V6);
+        swap.base_mint = "input".to_string();
+        swap.quote_mint = "output".to_string();
+        swap.route_plan.push(RouteStep::default());
+        let mut route = swap.clone();
+        route.instruction_index = 1;
+        route.product_fill = true;
+
+        let mut tables = Tables::new();
+        assert!(create_swap(&swap, &clock, &params, &mut tables));
+        assert!(!create_swap(&route, &clock, &params, &mut tables));
+        let entities: BTreeSet<String> = tables.to_entity_changes().entity_changes.into_iter()
+            .map(|change| format!("{}:{}", change.entity, change.id))
+            .collect();
+        assert_eq!(entities, BTreeSet::from(["RouteHop:swap--0-step-0".to_string(), "Swap:swap--0".to_string()]));
+    }
+
+    #[test]
+    fn classifies_instructions_by_the_enclosing_jupiter_instruction() {
+        // Top-level or wallet CPI into Jupiter, and its stray event data
+        assert_eq!(classify_instruction(None, true, false), InstructionClass::Outer);
+        assert_eq!(classify_instruction(None, true, true), InstructionClass::Other);
+        assert_eq!(classify_instruction(None, false, false), InstructionClass::Other);
+
+        // Under a Jupiter instruction: its self-CPI events and its CPIs into
+        // AMMs, the token program or another Jupiter program
+        assert_eq!(classify_instruction(Some(1), true, true), InstructionClass::Event);
+        assert_eq!(classify_instruction(Some(1), false, false), InstructionClass::Cpi);
+        assert_eq!(classify_instruction(Some(2), false, true), InstructionClass::Cpi);
+        assert_eq!(classify_instruction(Some(2), true, false), InstructionClass::Cpi);
+    }
 }
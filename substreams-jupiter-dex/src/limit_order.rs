@@ -0,0 +1,365 @@
+use borsh::BorshDeserialize;
+
+use crate::accounts::InstructionAccounts;
+use crate::pb::sf::jupiter::v1::{limit_order_event, liquidity_change::Kind, LimitOrderEvent, LiquidityChange};
+
+// Anchor instruction discriminators for the Jupiter Limit Order program,
+// computed as sha256("global:<snake_case_name>")[..8]
+pub const INITIALIZE_ORDER: [u8; 8] = [133, 110, 74, 175, 112, 159, 245, 159];
+pub const FILL_ORDER: [u8; 8] = [232, 122, 115, 25, 199, 143, 136, 162];
+pub const PRE_FLASH_FILL_ORDER: [u8; 8] = [240, 47, 153, 68, 13, 190, 225, 42];
+pub const FLASH_FILL_ORDER: [u8; 8] = [252, 104, 18, 134, 164, 78, 18, 140];
+pub const CANCEL_ORDER: [u8; 8] = [95, 129, 237, 240, 8, 49, 223, 132];
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InitializeOrderParams {
+    pub making_amount: u64,
+    pub taking_amount: u64,
+    pub expired_at: Option<i64>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct FillOrderParams {
+    pub making_amount: u64,
+    pub max_taking_amount: u64,
+}
+
+// Variants are named after the program's instructions
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitOrderInstruction {
+    InitializeOrder(InitializeOrderParams),
+    FillOrder(FillOrderParams),
+    // Lends the making amount out of the reserve to the taker, repaid by the
+    // flashFillOrder of the same transaction
+    PreFlashFillOrder { making_amount: u64 },
+    FlashFillOrder { max_taking_amount: u64 },
+    CancelOrder,
+}
+
+impl LimitOrderInstruction {
+    // Decodes a Jupiter Limit Order instruction, returns None for unknown
+    // discriminators or malformed args
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let (discriminator, mut args) = data.split_at(8);
+        match discriminator {
+            d if d == INITIALIZE_ORDER => InitializeOrderParams::deserialize(&mut args).ok().map(Self::InitializeOrder),
+            d if d == FILL_ORDER => FillOrderParams::deserialize(&mut args).ok().map(Self::FillOrder),
+            d if d == PRE_FLASH_FILL_ORDER => u64::deserialize(&mut args).ok()
+                .map(|making_amount| Self::PreFlashFillOrder { making_amount }),
+            d if d == FLASH_FILL_ORDER => u64::deserialize(&mut args).ok()
+                .map(|max_taking_amount| Self::FlashFillOrder { max_taking_amount }),
+            d if d == CANCEL_ORDER => Some(Self::CancelOrder),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InitializeOrder(_) => "initializeOrder",
+            Self::FillOrder(_) => "fillOrder",
+            Self::PreFlashFillOrder { .. } => "preFlashFillOrder",
+            Self::FlashFillOrder { .. } => "flashFillOrder",
+            Self::CancelOrder => "cancelOrder",
+        }
+    }
+
+    // Tokens escrowed in, or refunded from, the reserve of an order. Orders
+    // have no receipt token, so the order account stands in as both the pool
+    // and the output token.
+    pub fn liquidity_change(&self, accounts: &InstructionAccounts) -> Option<LiquidityChange> {
+        match self {
+            // base, maker, order, reserve, maker_input_account, input_mint,
+            // maker_output_account, referral, output_mint, ...
+            Self::InitializeOrder(params) => {
+                let order = accounts.key(2)?;
+                Some(LiquidityChange {
+                    kind: Kind::Deposit as i32,
+                    pool: order.clone(),
+                    input_tokens: vec![accounts.key(5)?, accounts.key(8)?],
+                    input_token_amounts: vec![params.making_amount.to_string(), "0".to_string()],
+                    output_token: order,
+                    output_token_amount: "0".to_string(),
+                    owner: accounts.key(1)?,
+                })
+            }
+            // order, reserve, maker, maker_input_account, system_program,
+            // token_program, input_mint, ... The remaining reserve is refunded.
+            Self::CancelOrder => {
+                let order = accounts.key(0)?;
+                Some(LiquidityChange {
+                    kind: Kind::Withdraw as i32,
+                    pool: order.clone(),
+                    input_tokens: vec![accounts.key(6)?],
+                    input_token_amounts: vec![accounts.balance(1).map_or(0, |c| c.delta()).to_string()],
+                    output_token: order,
+                    output_token_amount: "0".to_string(),
+                    owner: accounts.key(2)?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    // Creation, fill or cancellation of an order. Fills read the amounts
+    // from the balance changes, so a flash fill also covers the making amount
+    // lent out by its preFlashFillOrder. The reserve is left with the
+    // unfilled making amount, and is closed once the order is filled.
+    pub fn order_event(&self, accounts: &InstructionAccounts) -> Option<LimitOrderEvent> {
+        match self {
+            // base, maker, order, reserve, maker_input_account, input_mint,
+            // maker_output_account, referral, output_mint, ...
+            Self::InitializeOrder(params) => Some(LimitOrderEvent {
+                kind: limit_order_event::Kind::Create as i32,
+                order: accounts.key(2)?,
+                maker: accounts.key(1)?,
+                input_mint: accounts.key(5)?,
+                output_mint: accounts.key(8)?,
+                making_amount: params.making_amount.to_string(),
+                taking_amount: params.taking_amount.to_string(),
+                remaining_making_amount: params.making_amount.to_string(),
+                expired_at: params.expired_at.unwrap_or(0),
+                ..Default::default()
+            }),
+            // order, reserve, maker, taker, taker_output_account,
+            // maker_output_account, taker_input_account, fee_authority, ...
+            Self::FillOrder(params) => Some(LimitOrderEvent {
+                kind: limit_order_event::Kind::Fill as i32,
+                order: accounts.key(0)?,
+                maker: accounts.key(2)?,
+                taker: accounts.key(3)?,
+                input_mint: accounts.mint(1).or_else(|| accounts.mint(4)).unwrap_or_default(),
+                output_mint: accounts.mint(5).or_else(|| accounts.mint(6)).unwrap_or_default(),
+                making_amount: params.making_amount.to_string(),
+                taking_amount: taking_amount(accounts, 5, 6).to_string(),
+                remaining_making_amount: accounts.balance(1).map_or(0, |c| c.post_amount).to_string(),
+                ..Default::default()
+            }),
+            // order, reserve, maker, taker, maker_output_account,
+            // taker_input_account, fee_authority, ...
+            Self::FlashFillOrder { .. } => Some(LimitOrderEvent {
+                kind: limit_order_event::Kind::Fill as i32,
+                order: accounts.key(0)?,
+                maker: accounts.key(2)?,
+                taker: accounts.key(3)?,
+                input_mint: accounts.mint(1).unwrap_or_default(),
+                output_mint: accounts.mint(4).or_else(|| accounts.mint(5)).unwrap_or_default(),
+                making_amount: accounts.balance(1).map_or(0, |c| c.delta()).to_string(),
+                taking_amount: taking_amount(accounts, 4, 5).to_string(),
+                remaining_making_amount: accounts.balance(1).map_or(0, |c| c.post_amount).to_string(),
+                ..Default::default()
+            }),
+            // order, reserve, maker, maker_input_account, system_program,
+            // token_program, input_mint, ...
+            Self::CancelOrder => Some(LimitOrderEvent {
+                kind: limit_order_event::Kind::Cancel as i32,
+                order: accounts.key(0)?,
+                maker: accounts.key(2)?,
+                input_mint: accounts.key(6).unwrap_or_default(),
+                ..Default::default()
+            }),
+            Self::PreFlashFillOrder { .. } => None,
+        }
+    }
+}
+
+// Output tokens paid to the maker. Orders for native SOL are paid to the
+// maker's wallet, which has no token balance, so the taker's side is used.
+fn taking_amount(accounts: &InstructionAccounts, maker_output: usize, taker_input: usize) -> u64 {
+    accounts.balance(maker_output)
+        .or_else(|| accounts.balance(taker_input))
+        .map_or(0, |c| c.delta())
+}
+
+#[cfg(test)]
+mod tests {
+    use substreams_solana::pb::sf::solana::r#type::v1::{
+        ConfirmedTransaction, Message, TokenBalance, Transaction, TransactionStatusMeta, UiTokenAmount,
+    };
+    use substreams_solana::Address;
+
+    use super::*;
+    use crate::accounts::ResolvedAccounts;
+    use crate::balances::BalanceChanges;
+    use crate::events::instruction_data;
+
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const WSOL: &str = "So11111111111111111111111111111111111111112";
+
+    // Instruction account n is the transaction key [n; 32]
+    fn key(n: u8) -> String {
+        bs58::encode([n; 32]).into_string()
+    }
+
+    fn token_balance(account_index: u32, mint: &str, amount: u64) -> TokenBalance {
+        TokenBalance {
+            account_index,
+            mint: mint.to_string(),
+            ui_token_amount: Some(UiTokenAmount { amount: amount.to_string(), decimals: 6, ..Default::default() }),
+            ..Default::default()
+        }
+    }
+
+    // Transaction with `accounts` keys and the given (account, mint, pre, post)
+    // token balances
+    fn transaction(accounts: u8, balances: &[(u32, &str, u64, u64)]) -> ConfirmedTransaction {
+        ConfirmedTransaction {
+            transaction: Some(Transaction {
+                message: Some(Message {
+                    account_keys: (0..accounts).map(|n| vec![n; 32]).collect(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            meta: Some(TransactionStatusMeta {
+                pre_token_balances: balances.iter().map(|(index, mint, pre, _)| token_balance(*index, mint, *pre)).collect(),
+                post_token_balances: balances.iter().map(|(index, mint, _, post)| token_balance(*index, mint, *post)).collect(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    // Runs `f` with the accounts of an instruction listing every key of the
+    // transaction in order
+    fn with_accounts<T>(tx: &ConfirmedTransaction, f: impl FnOnce(&InstructionAccounts) -> T) -> T {
+        let resolved = ResolvedAccounts::from_transaction(tx);
+        let balances = BalanceChanges::from_meta(tx.meta.as_ref().unwrap());
+        let keys = &tx.transaction.as_ref().unwrap().message.as_ref().unwrap().account_keys;
+        let accounts = InstructionAccounts {
+            accounts: keys.iter().map(Address).collect(),
+            resolved: &resolved,
+            balances: &balances,
+        };
+        f(&accounts)
+    }
+
+    #[test]
+    fn decodes_instructions() {
+        let data = instruction_data(&INITIALIZE_ORDER, &[&1_000u64.to_le_bytes(), &250u64.to_le_bytes(), &[1], &1_700_000_000i64.to_le_bytes()]);
+        assert_eq!(LimitOrderInstruction::decode(&data), Some(LimitOrderInstruction::InitializeOrder(InitializeOrderParams {
+            making_amount: 1_000,
+            taking_amount: 250,
+            expired_at: Some(1_700_000_000),
+        })));
+
+        let data = instruction_data(&FILL_ORDER, &[&600u64.to_le_bytes(), &160u64.to_le_bytes()]);
+        assert_eq!(LimitOrderInstruction::decode(&data), Some(LimitOrderInstruction::FillOrder(FillOrderParams {
+            making_amount: 600,
+            max_taking_amount: 160,
+        })));
+
+        let data = instruction_data(&PRE_FLASH_FILL_ORDER, &[&600u64.to_le_bytes()]);
+        assert_eq!(LimitOrderInstruction::decode(&data), Some(LimitOrderInstruction::PreFlashFillOrder { making_amount: 600 }));
+
+        let data = instruction_data(&FLASH_FILL_ORDER, &[&160u64.to_le_bytes()]);
+        assert_eq!(LimitOrderInstruction::decode(&data), Some(LimitOrderInstruction::FlashFillOrder { max_taking_amount: 160 }));
+
+        assert_eq!(LimitOrderInstruction::decode(&CANCEL_ORDER), Some(LimitOrderInstruction::CancelOrder));
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_instructions() {
+        assert_eq!(LimitOrderInstruction::decode(&[0; 16]), None);
+        assert_eq!(LimitOrderInstruction::decode(&FILL_ORDER[..4]), None);
+        assert_eq!(LimitOrderInstruction::decode(&instruction_data(&FILL_ORDER, &[&600u64.to_le_bytes()])), None);
+    }
+
+    #[test]
+    fn initialize_order_escrows_the_making_amount() {
+        let data = instruction_data(&INITIALIZE_ORDER, &[&1_000u64.to_le_bytes(), &250u64.to_le_bytes(), &[0]]);
+        let instruction = LimitOrderInstruction::decode(&data).unwrap();
+        let tx = transaction(12, &[(3, USDC, 0, 1_000), (4, USDC, 1_000, 0)]);
+
+        let (event, change) = with_accounts(&tx, |accounts| (instruction.order_event(accounts), instruction.liquidity_change(accounts)));
+        let event = event.unwrap();
+        assert_eq!(event.kind(), limit_order_event::Kind::Create);
+        assert_eq!(event.order, key(2));
+        assert_eq!(event.maker, key(1));
+        assert_eq!(event.input_mint, key(5));
+        assert_eq!(event.output_mint, key(8));
+        assert_eq!(event.making_amount, "1000");
+        assert_eq!(event.taking_amount, "250");
+        assert_eq!(event.remaining_making_amount, "1000");
+        assert_eq!(event.expired_at, 0);
+
+        let change = change.unwrap();
+        assert_eq!(change.kind(), Kind::Deposit);
+        assert_eq!(change.pool, key(2));
+        assert_eq!(change.owner, key(1));
+        assert_eq!(change.input_tokens, vec![key(5), key(8)]);
+        assert_eq!(change.input_token_amounts, vec!["1000", "0"]);
+    }
+
+    #[test]
+    fn fill_order_reads_amounts_from_reserve_and_maker_deltas() {
+        let data = instruction_data(&FILL_ORDER, &[&600u64.to_le_bytes(), &160u64.to_le_bytes()]);
+        let instruction = LimitOrderInstruction::decode(&data).unwrap();
+        // reserve, taker_output_account, maker_output_account, taker_input_account
+        let tx = transaction(12, &[
+            (1, USDC, 1_000, 400),
+            (4, USDC, 0, 600),
+            (5, WSOL, 0, 150),
+            (6, WSOL, 150, 0),
+        ]);
+
+        let event = with_accounts(&tx, |accounts| instruction.order_event(accounts)).unwrap();
+        assert_eq!(event.kind(), limit_order_event::Kind::Fill);
+        assert_eq!(event.order, key(0));
+        assert_eq!(event.maker, key(2));
+        assert_eq!(event.taker, key(3));
+        assert_eq!(event.input_mint, USDC);
+        assert_eq!(event.output_mint, WSOL);
+        assert_eq!(event.making_amount, "600");
+        assert_eq!(event.taking_amount, "150");
+        assert_eq!(event.remaining_making_amount, "400");
+    }
+
+    #[test]
+    fn flash_fill_reads_the_making_amount_from_the_reserve_delta() {
+        let data = instruction_data(&FLASH_FILL_ORDER, &[&160u64.to_le_bytes()]);
+        let instruction = LimitOrderInstruction::decode(&data).unwrap();
+        // The order sells for native SOL, paid to the maker's wallet at
+        // account 4, so the taking amount is read from the taker's side
+        let tx = transaction(12, &[(1, USDC, 1_000, 0), (5, WSOL, 250, 0)]);
+
+        let event = with_accounts(&tx, |accounts| instruction.order_event(accounts)).unwrap();
+        assert_eq!(event.kind(), limit_order_event::Kind::Fill);
+        assert_eq!(event.input_mint, USDC);
+        assert_eq!(event.output_mint, WSOL);
+        assert_eq!(event.making_amount, "1000");
+        assert_eq!(event.taking_amount, "250");
+        assert_eq!(event.remaining_making_amount, "0");
+    }
+
+    #[test]
+    fn cancel_order_refunds_the_reserve() {
+        let instruction = LimitOrderInstruction::decode(&CANCEL_ORDER).unwrap();
+        let tx = transaction(8, &[(1, USDC, 400, 0), (3, USDC, 0, 400)]);
+
+        let (event, change) = with_accounts(&tx, |accounts| (instruction.order_event(accounts), instruction.liquidity_change(accounts)));
+        let event = event.unwrap();
+        assert_eq!(event.kind(), limit_order_event::Kind::Cancel);
+        assert_eq!(event.order, key(0));
+        assert_eq!(event.maker, key(2));
+        assert_eq!(event.input_mint, key(6));
+
+        let change = change.unwrap();
+        assert_eq!(change.kind(), Kind::Withdraw);
+        assert_eq!(change.owner, key(2));
+        assert_eq!(change.input_tokens, vec![key(6)]);
+        assert_eq!(change.input_token_amounts, vec!["400"]);
+    }
+
+    #[test]
+    fn pre_flash_fill_has_no_order_event() {
+        let data = instruction_data(&PRE_FLASH_FILL_ORDER, &[&600u64.to_le_bytes()]);
+        let instruction = LimitOrderInstruction::decode(&data).unwrap();
+        let tx = transaction(8, &[]);
+
+        assert_eq!(with_accounts(&tx, |accounts| instruction.order_event(accounts)), None);
+    }
+}
@@ -0,0 +1,89 @@
+use substreams::errors::Error;
+
+use crate::programs::{decode_pubkey, ProgramSet};
+
+// Verbosity of the map module logs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum LogLevel {
+    // Only traced transactions are logged
+    #[default]
+    Off,
+    // Block summaries and Jupiter instructions
+    Info,
+    // Every transaction, entity and product event, and balance details as if
+    // all transactions were traced
+    Debug,
+}
+
+// Module params, as a query string of comma separated lists, e.g.
+// `signatures=2k9An8...,5xT3...&slots=322167085&traders=7dGb...&log_level=debug`.
+// A transaction is traced if it matches any of the signatures, slots or
+// traders, which logs its token balances and instruction data.
+// `programs=JUP6Lk...:v6,jupoNj...:limit_order:Limit Order` replaces the
+// built-in set of indexed programs, with their kind and optional label.
+#[derive(Debug, Default)]
+pub struct Params {
+    signatures: Vec<Vec<u8>>,
+    slots: Vec<u64>,
+    traders: Vec<[u8; 32]>,
+    pub log_level: LogLevel,
+    pub programs: ProgramSet,
+}
+
+impl Params {
+    pub fn parse(params: &str) -> Result<Self, Error> {
+        let mut parsed = Params::default();
+
+        for pair in params.split('&').map(str::trim).filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=')
+                .ok_or_else(|| Error::msg(format!("invalid param `{}`, expected key=value", pair)))?;
+            let values = value.split(',').map(str::trim).filter(|v| !v.is_empty());
+
+            match key.trim() {
+                "signatures" => {
+                    for signature in values {
+                        parsed.signatures.push(bs58::decode(signature).into_vec()
+                            .map_err(|_| Error::msg(format!("invalid signature `{}`", signature)))?);
+                    }
+                }
+                "slots" => {
+                    for slot in values {
+                        parsed.slots.push(slot.parse::<u64>()
+                            .map_err(|_| Error::msg(format!("invalid slot `{}`", slot)))?);
+                    }
+                }
+                "traders" => {
+                    for trader in values {
+                        parsed.traders.push(decode_pubkey(trader)
+                            .ok_or_else(|| Error::msg(format!("invalid trader `{}`", trader)))?);
+                    }
+                }
+                "programs" => parsed.programs = ProgramSet::parse(value)?,
+                "log_level" => {
+                    parsed.log_level = match value.trim() {
+                        "off" => LogLevel::Off,
+                        "info" => LogLevel::Info,
+                        "debug" => LogLevel::Debug,
+                        level => return Err(Error::msg(format!("invalid log_level `{}`, expected off, info or debug", level))),
+                    }
+                }
+                other => return Err(Error::msg(format!("unknown param `{}`", other))),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    // Returns true if logs of the given level are enabled
+    pub fn logs(&self, level: LogLevel) -> bool {
+        self.log_level >= level
+    }
+
+    // Matches the raw signature and trader pubkey of a transaction
+    pub fn is_traced(&self, signature: &[u8], slot: u64, trader: &[u8]) -> bool {
+        self.log_level == LogLevel::Debug
+            || self.signatures.iter().any(|s| s.as_slice() == signature)
+            || self.slots.contains(&slot)
+            || self.traders.iter().any(|t| t.as_slice() == trader)
+    }
+}
@@ -1 +1,10 @@
-// Empty module - all types are imported directly in lib.rs
+// @generated
+pub mod sf {
+    pub mod jupiter {
+        // @@protoc_insertion_point(attribute:sf.jupiter.v1)
+        pub mod v1 {
+            include!("sf.jupiter.v1.rs");
+            // @@protoc_insertion_point(sf.jupiter.v1)
+        }
+    }
+}
@@ -0,0 +1,524 @@
+// @generated
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JupiterTransactions {
+    #[prost(message, repeated, tag = "1")]
+    pub transactions: ::prost::alloc::vec::Vec<JupiterTransaction>,
+}
+/// A Jupiter instruction and the trade it executed for the trader
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct JupiterTransaction {
+    #[prost(uint64, tag = "1")]
+    pub slot: u64,
+    #[prost(uint64, tag = "2")]
+    pub timestamp: u64,
+    #[prost(bytes = "vec", tag = "3")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "4")]
+    pub program_id: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", repeated, tag = "5")]
+    pub account_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    #[prost(string, tag = "6")]
+    pub tx_id: ::prost::alloc::string::String,
+    #[prost(int64, tag = "7")]
+    pub tx_index: i64,
+    #[prost(string, tag = "8")]
+    pub signer: ::prost::alloc::string::String,
+    /// Token spent by the trader, empty if no spent side was found
+    #[prost(string, tag = "10")]
+    pub base_mint: ::prost::alloc::string::String,
+    /// Token received by the trader, empty if no received side was found
+    #[prost(string, tag = "11")]
+    pub quote_mint: ::prost::alloc::string::String,
+    /// Decimal-normalized amounts, use base_raw_amount/quote_raw_amount for exact values
+    #[prost(double, tag = "12")]
+    pub base_amount: f64,
+    #[prost(double, tag = "13")]
+    pub quote_amount: f64,
+    #[prost(string, tag = "14")]
+    pub trader: ::prost::alloc::string::String,
+    #[prost(bool, tag = "17")]
+    pub is_inner_instruction: bool,
+    #[prost(uint32, tag = "18")]
+    pub instruction_index: u32,
+    #[prost(string, tag = "19")]
+    pub instruction_type: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "20")]
+    pub inner_instruction_index: u32,
+    #[prost(string, tag = "21")]
+    pub outer_program: ::prost::alloc::string::String,
+    #[prost(string, tag = "22")]
+    pub inner_program: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "23")]
+    pub txn_fee_lamports: u64,
+    #[prost(int64, tag = "24")]
+    pub signer_lamports_change: i64,
+    #[prost(string, repeated, tag = "25")]
+    pub outer_executing_accounts: ::prost::alloc::vec::Vec<
+        ::prost::alloc::string::String,
+    >,
+    #[prost(int64, tag = "26")]
+    pub trader_lamports_change: i64,
+    #[prost(message, repeated, tag = "27")]
+    pub trader_token_balance_changes: ::prost::alloc::vec::Vec<TokenBalanceChange>,
+    /// Exact native-unit amounts of base_amount/quote_amount
+    #[prost(string, tag = "28")]
+    pub base_raw_amount: ::prost::alloc::string::String,
+    #[prost(string, tag = "29")]
+    pub quote_raw_amount: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "30")]
+    pub base_decimals: u32,
+    #[prost(uint32, tag = "31")]
+    pub quote_decimals: u32,
+    /// Decoded args of the Jupiter v6 route instructions
+    #[prost(message, optional, tag = "32")]
+    pub quote: ::core::option::Option<SwapQuote>,
+    #[prost(message, repeated, tag = "33")]
+    pub route_plan: ::prost::alloc::vec::Vec<RouteStep>,
+    /// SwapEvents emitted under this instruction, one per executed hop
+    #[prost(message, repeated, tag = "34")]
+    pub swap_events: ::prost::alloc::vec::Vec<SwapEvent>,
+    /// Owner of the destination token account, trader is the user transfer authority
+    #[prost(string, tag = "35")]
+    pub recipient: ::prost::alloc::string::String,
+    /// Set for instructions moving tokens in or out of a Jupiter product
+    #[prost(message, optional, tag = "36")]
+    pub liquidity_change: ::core::option::Option<LiquidityChange>,
+    /// Orders created, filled or cancelled by a Limit Order instruction
+    #[prost(message, repeated, tag = "37")]
+    pub limit_orders: ::prost::alloc::vec::Vec<LimitOrderEvent>,
+    /// Opened, Filled and Closed events emitted under a DCA instruction
+    #[prost(message, repeated, tag = "38")]
+    pub dca_events: ::prost::alloc::vec::Vec<DcaEvent>,
+    /// Position events emitted under a Perps instruction
+    #[prost(message, repeated, tag = "39")]
+    pub perp_events: ::prost::alloc::vec::Vec<PerpEvent>,
+    /// Set on aggregator instructions routing a keeper's DCA or limit order
+    /// fill, whose volume is reported by the fill instead of a swap
+    #[prost(bool, tag = "40")]
+    pub product_fill: bool,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TokenBalanceChange {
+    #[prost(string, tag = "1")]
+    pub mint: ::prost::alloc::string::String,
+    /// Signed, decimal-normalized change
+    #[prost(double, tag = "2")]
+    pub amount: f64,
+    /// Signed change in native units
+    #[prost(string, tag = "3")]
+    pub raw_amount: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "4")]
+    pub decimals: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SwapQuote {
+    #[prost(bool, tag = "1")]
+    pub exact_out: bool,
+    /// Not part of the args of the token ledger routes
+    #[prost(uint64, optional, tag = "2")]
+    pub in_amount: ::core::option::Option<u64>,
+    #[prost(uint64, tag = "3")]
+    pub out_amount: u64,
+    #[prost(uint32, tag = "4")]
+    pub slippage_bps: u32,
+    #[prost(uint32, tag = "5")]
+    pub platform_fee_bps: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct RouteStep {
+    /// Underlying AMM, e.g. Raydium or Whirlpool
+    #[prost(string, tag = "1")]
+    pub amm: ::prost::alloc::string::String,
+    #[prost(uint32, tag = "2")]
+    pub percent: u32,
+    #[prost(uint32, tag = "3")]
+    pub input_index: u32,
+    #[prost(uint32, tag = "4")]
+    pub output_index: u32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SwapEvent {
+    #[prost(string, tag = "1")]
+    pub amm: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub input_mint: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "3")]
+    pub input_amount: u64,
+    #[prost(string, tag = "4")]
+    pub output_mint: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "5")]
+    pub output_amount: u64,
+    #[prost(uint32, tag = "6")]
+    pub instruction_index: u32,
+    #[prost(uint32, tag = "7")]
+    pub inner_instruction_index: u32,
+}
+/// A pool the first time it is traded through, kept by store_pools
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Pool {
+    /// {program id}-{token in}-{token out}
+    #[prost(string, tag = "1")]
+    pub id: ::prost::alloc::string::String,
+    #[prost(string, tag = "2")]
+    pub protocol: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "3")]
+    pub input_tokens: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    #[prost(uint64, tag = "4")]
+    pub created_slot: u64,
+    #[prost(uint64, tag = "5")]
+    pub created_timestamp: u64,
+}
+/// Usage metrics of a protocol for one day, kept by store_usage_snapshots
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UsageSnapshot {
+    #[prost(string, tag = "1")]
+    pub protocol: ::prost::alloc::string::String,
+    /// Days since Unix epoch
+    #[prost(uint64, tag = "2")]
+    pub day: u64,
+    #[prost(int64, tag = "3")]
+    pub daily_active_users: i64,
+    #[prost(int64, tag = "4")]
+    pub cumulative_unique_users: i64,
+    #[prost(int64, tag = "5")]
+    pub daily_transaction_count: i64,
+    #[prost(int64, tag = "6")]
+    pub daily_deposit_count: i64,
+    #[prost(int64, tag = "7")]
+    pub daily_withdraw_count: i64,
+    #[prost(int64, tag = "8")]
+    pub daily_swap_count: i64,
+    #[prost(int64, tag = "9")]
+    pub total_pool_count: i64,
+    /// Block of the last update within the day
+    #[prost(uint64, tag = "10")]
+    pub block_number: u64,
+    #[prost(int64, tag = "11")]
+    pub timestamp: i64,
+}
+/// Tokens deposited into or withdrawn from a Jupiter product: JLP liquidity,
+/// DCA vaults or limit order escrows
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LiquidityChange {
+    #[prost(enumeration = "liquidity_change::Kind", tag = "1")]
+    pub kind: i32,
+    /// JLP custody, DCA or limit order account the tokens move through
+    #[prost(string, tag = "2")]
+    pub pool: ::prost::alloc::string::String,
+    #[prost(string, repeated, tag = "3")]
+    pub input_tokens: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Native-unit amounts, in the order of input_tokens
+    #[prost(string, repeated, tag = "4")]
+    pub input_token_amounts: ::prost::alloc::vec::Vec<::prost::alloc::string::String>,
+    /// Token minted or burned for the position, the position account itself
+    /// for products without a receipt token
+    #[prost(string, tag = "5")]
+    pub output_token: ::prost::alloc::string::String,
+    #[prost(string, tag = "6")]
+    pub output_token_amount: ::prost::alloc::string::String,
+    /// Account that sent the tokens for deposits, received them for withdrawals
+    #[prost(string, tag = "7")]
+    pub owner: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `LiquidityChange`.
+pub mod liquidity_change {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Kind {
+        Deposit = 0,
+        Withdraw = 1,
+    }
+    impl Kind {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Kind::Deposit => "DEPOSIT",
+                Kind::Withdraw => "WITHDRAW",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "DEPOSIT" => Some(Self::Deposit),
+                "WITHDRAW" => Some(Self::Withdraw),
+                _ => None,
+            }
+        }
+    }
+}
+/// Lifecycle event of a limit order
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LimitOrderEvent {
+    #[prost(enumeration = "limit_order_event::Kind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, tag = "2")]
+    pub order: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub maker: ::prost::alloc::string::String,
+    /// Empty when not part of the instruction, cancellations carry no output
+    /// mint
+    #[prost(string, tag = "4")]
+    pub input_mint: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub output_mint: ::prost::alloc::string::String,
+    /// Native-unit amounts of the whole order on creation, of the filled part
+    /// on fills
+    #[prost(string, tag = "6")]
+    pub making_amount: ::prost::alloc::string::String,
+    #[prost(string, tag = "7")]
+    pub taking_amount: ::prost::alloc::string::String,
+    /// Making amount left in the reserve
+    #[prost(string, tag = "8")]
+    pub remaining_making_amount: ::prost::alloc::string::String,
+    /// Unix timestamp the order expires at, 0 if it never does
+    #[prost(int64, tag = "9")]
+    pub expired_at: i64,
+    /// Keeper filling the order
+    #[prost(string, tag = "10")]
+    pub taker: ::prost::alloc::string::String,
+}
+/// Nested message and enum types in `LimitOrderEvent`.
+pub mod limit_order_event {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Kind {
+        Create = 0,
+        Fill = 1,
+        Cancel = 2,
+    }
+    impl Kind {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Kind::Create => "CREATE",
+                Kind::Fill => "FILL",
+                Kind::Cancel => "CANCEL",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "CREATE" => Some(Self::Create),
+                "FILL" => Some(Self::Fill),
+                "CANCEL" => Some(Self::Cancel),
+                _ => None,
+            }
+        }
+    }
+}
+/// Anchor event of a DCA position
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DcaEvent {
+    #[prost(enumeration = "dca_event::Kind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, tag = "2")]
+    pub dca: ::prost::alloc::string::String,
+    #[prost(string, tag = "3")]
+    pub user: ::prost::alloc::string::String,
+    #[prost(string, tag = "4")]
+    pub input_mint: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub output_mint: ::prost::alloc::string::String,
+    /// Opened and Closed, native units
+    #[prost(uint64, tag = "6")]
+    pub in_deposited: u64,
+    #[prost(uint64, tag = "7")]
+    pub in_amount_per_cycle: u64,
+    /// Seconds between two cycles
+    #[prost(int64, tag = "8")]
+    pub cycle_frequency: i64,
+    /// Filled, native units
+    #[prost(uint64, tag = "9")]
+    pub in_amount: u64,
+    #[prost(uint64, tag = "10")]
+    pub out_amount: u64,
+    #[prost(string, tag = "11")]
+    pub fee_mint: ::prost::alloc::string::String,
+    #[prost(uint64, tag = "12")]
+    pub fee: u64,
+    /// Closed, native units
+    #[prost(uint64, tag = "13")]
+    pub total_in_withdrawn: u64,
+    #[prost(uint64, tag = "14")]
+    pub total_out_withdrawn: u64,
+    #[prost(uint64, tag = "15")]
+    pub unfilled_amount: u64,
+    /// False when a keeper closed the position once it was filled
+    #[prost(bool, tag = "16")]
+    pub user_closed: bool,
+    #[prost(uint32, tag = "17")]
+    pub instruction_index: u32,
+    #[prost(uint32, tag = "18")]
+    pub inner_instruction_index: u32,
+}
+/// Nested message and enum types in `DcaEvent`.
+pub mod dca_event {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Kind {
+        Opened = 0,
+        Filled = 1,
+        Closed = 2,
+    }
+    impl Kind {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Kind::Opened => "OPENED",
+                Kind::Filled => "FILLED",
+                Kind::Closed => "CLOSED",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "OPENED" => Some(Self::Opened),
+                "FILLED" => Some(Self::Filled),
+                "CLOSED" => Some(Self::Closed),
+                _ => None,
+            }
+        }
+    }
+}
+/// Anchor event of a Perps position. USD amounts have 6 decimals.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PerpEvent {
+    #[prost(enumeration = "perp_event::Kind", tag = "1")]
+    pub kind: i32,
+    #[prost(string, tag = "2")]
+    pub position: ::prost::alloc::string::String,
+    /// Not part of liquidation events
+    #[prost(string, tag = "3")]
+    pub owner: ::prost::alloc::string::String,
+    /// Custody of the traded token, and of the collateral token
+    #[prost(string, tag = "4")]
+    pub custody: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub collateral_custody: ::prost::alloc::string::String,
+    /// 1 for long, 2 for short
+    #[prost(uint32, tag = "6")]
+    pub side: u32,
+    /// Size of the position after the event
+    #[prost(uint64, tag = "7")]
+    pub position_size_usd: u64,
+    #[prost(uint64, tag = "8")]
+    pub size_usd_delta: u64,
+    #[prost(uint64, tag = "9")]
+    pub collateral_usd_delta: u64,
+    #[prost(uint64, tag = "10")]
+    pub price: u64,
+    /// Decrease and liquidation
+    #[prost(bool, tag = "11")]
+    pub has_profit: bool,
+    #[prost(uint64, tag = "12")]
+    pub pnl_delta: u64,
+    /// Liquidation
+    #[prost(string, tag = "13")]
+    pub collateral_mint: ::prost::alloc::string::String,
+    /// Native-unit collateral returned to the owner
+    #[prost(uint64, tag = "14")]
+    pub transfer_amount_token: u64,
+    /// Fees of every event kind, on top of the liquidation fee
+    #[prost(uint64, tag = "15")]
+    pub fee_usd: u64,
+    #[prost(uint64, tag = "16")]
+    pub liquidation_fee_usd: u64,
+    #[prost(uint32, tag = "17")]
+    pub instruction_index: u32,
+    #[prost(uint32, tag = "18")]
+    pub inner_instruction_index: u32,
+}
+/// Nested message and enum types in `PerpEvent`.
+pub mod perp_event {
+    #[derive(
+        Clone,
+        Copy,
+        Debug,
+        PartialEq,
+        Eq,
+        Hash,
+        PartialOrd,
+        Ord,
+        ::prost::Enumeration
+    )]
+    #[repr(i32)]
+    pub enum Kind {
+        Increase = 0,
+        Decrease = 1,
+        Liquidation = 2,
+    }
+    impl Kind {
+        /// String value of the enum field names used in the ProtoBuf definition.
+        ///
+        /// The values are not transformed in any way and thus are considered stable
+        /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+        pub fn as_str_name(&self) -> &'static str {
+            match self {
+                Kind::Increase => "INCREASE",
+                Kind::Decrease => "DECREASE",
+                Kind::Liquidation => "LIQUIDATION",
+            }
+        }
+        /// Creates an enum from field names used in the ProtoBuf definition.
+        pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+            match value {
+                "INCREASE" => Some(Self::Increase),
+                "DECREASE" => Some(Self::Decrease),
+                "LIQUIDATION" => Some(Self::Liquidation),
+                _ => None,
+            }
+        }
+    }
+}
+// @@protoc_insertion_point(module)
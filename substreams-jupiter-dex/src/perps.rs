@@ -0,0 +1,560 @@
+use borsh::BorshDeserialize;
+
+use crate::accounts::InstructionAccounts;
+use crate::events::event_args;
+use crate::pb::sf::jupiter::v1::{liquidity_change::Kind, LiquidityChange};
+
+// Anchor instruction discriminators for the Jupiter Perpetuals program,
+// computed as sha256("global:<snake_case_name>")[..8]
+pub const ADD_LIQUIDITY2: [u8; 8] = [228, 162, 78, 28, 70, 219, 116, 115];
+pub const REMOVE_LIQUIDITY2: [u8; 8] = [230, 215, 82, 127, 241, 101, 227, 146];
+pub const CREATE_INCREASE_POSITION_MARKET_REQUEST: [u8; 8] = [184, 85, 199, 24, 105, 171, 156, 56];
+pub const CREATE_DECREASE_POSITION_MARKET_REQUEST: [u8; 8] = [74, 198, 195, 86, 193, 99, 1, 79];
+pub const INCREASE_POSITION4: [u8; 8] = [67, 147, 53, 23, 43, 57, 16, 67];
+pub const DECREASE_POSITION4: [u8; 8] = [185, 161, 114, 175, 96, 148, 3, 170];
+pub const INSTANT_INCREASE_POSITION: [u8; 8] = [164, 126, 68, 182, 223, 166, 64, 183];
+pub const INSTANT_DECREASE_POSITION: [u8; 8] = [46, 23, 240, 44, 30, 138, 94, 140];
+pub const LIQUIDATE_FULL_POSITION4: [u8; 8] = [64, 176, 88, 51, 168, 188, 156, 175];
+
+// Anchor event discriminators, sha256("event:<Name>")[..8]
+pub const INCREASE_POSITION_EVENT: [u8; 8] = [245, 113, 85, 52, 214, 187, 153, 132];
+pub const DECREASE_POSITION_EVENT: [u8; 8] = [64, 156, 43, 74, 109, 131, 16, 127];
+pub const LIQUIDATE_FULL_POSITION_EVENT: [u8; 8] = [128, 101, 71, 168, 128, 72, 86, 84];
+pub const INSTANT_INCREASE_POSITION_EVENT: [u8; 8] = [205, 236, 57, 4, 209, 106, 87, 69];
+pub const INSTANT_DECREASE_POSITION_EVENT: [u8; 8] = [171, 173, 106, 25, 239, 190, 58, 59];
+
+// Side of a position, as encoded by the program
+pub const SIDE_LONG: u8 = 1;
+pub const SIDE_SHORT: u8 = 2;
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct AddLiquidity2Params {
+    pub token_amount_in: u64,
+    pub min_lp_amount_out: u64,
+    pub token_amount_pre_swap: Option<u64>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct RemoveLiquidity2Params {
+    pub lp_amount_in: u64,
+    pub min_amount_out: u64,
+}
+
+// Position instructions are only named, the amounts traded are taken from
+// the events they emit. Market requests are executed by a keeper with
+// increasePosition4/decreasePosition4 in a later transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PerpsInstruction {
+    AddLiquidity2(AddLiquidity2Params),
+    RemoveLiquidity2(RemoveLiquidity2Params),
+    CreateIncreasePositionMarketRequest,
+    CreateDecreasePositionMarketRequest,
+    IncreasePosition4,
+    DecreasePosition4,
+    InstantIncreasePosition,
+    InstantDecreasePosition,
+    LiquidateFullPosition4,
+}
+
+impl PerpsInstruction {
+    // Decodes a Jupiter Perpetuals instruction, returns None for unknown
+    // discriminators or malformed args
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let (discriminator, mut args) = data.split_at(8);
+        match discriminator {
+            d if d == ADD_LIQUIDITY2 => AddLiquidity2Params::deserialize(&mut args).ok().map(Self::AddLiquidity2),
+            d if d == REMOVE_LIQUIDITY2 => RemoveLiquidity2Params::deserialize(&mut args).ok().map(Self::RemoveLiquidity2),
+            d if d == CREATE_INCREASE_POSITION_MARKET_REQUEST => Some(Self::CreateIncreasePositionMarketRequest),
+            d if d == CREATE_DECREASE_POSITION_MARKET_REQUEST => Some(Self::CreateDecreasePositionMarketRequest),
+            d if d == INCREASE_POSITION4 => Some(Self::IncreasePosition4),
+            d if d == DECREASE_POSITION4 => Some(Self::DecreasePosition4),
+            d if d == INSTANT_INCREASE_POSITION => Some(Self::InstantIncreasePosition),
+            d if d == INSTANT_DECREASE_POSITION => Some(Self::InstantDecreasePosition),
+            d if d == LIQUIDATE_FULL_POSITION4 => Some(Self::LiquidateFullPosition4),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::AddLiquidity2(_) => "addLiquidity2",
+            Self::RemoveLiquidity2(_) => "removeLiquidity2",
+            Self::CreateIncreasePositionMarketRequest => "createIncreasePositionMarketRequest",
+            Self::CreateDecreasePositionMarketRequest => "createDecreasePositionMarketRequest",
+            Self::IncreasePosition4 => "increasePosition4",
+            Self::DecreasePosition4 => "decreasePosition4",
+            Self::InstantIncreasePosition => "instantIncreasePosition",
+            Self::InstantDecreasePosition => "instantDecreasePosition",
+            Self::LiquidateFullPosition4 => "liquidateFullPosition4",
+        }
+    }
+
+    // JLP minted for, or burned against, the token of a custody. Each custody
+    // of the JLP pool holds a single token, so it stands in as the pool. Both
+    // instructions share the layout owner, funding/receiving account,
+    // lp_token_account, transfer_authority, perpetuals, pool, custody,
+    // custody price accounts, custody_token_account, lp_token_mint, ...
+    pub fn liquidity_change(&self, accounts: &InstructionAccounts) -> Option<LiquidityChange> {
+        if !matches!(self, Self::AddLiquidity2(_) | Self::RemoveLiquidity2(_)) {
+            return None;
+        }
+
+        let owner = accounts.key(0)?;
+        let token = accounts.mint(1)?;
+        let pool = accounts.key(6)?;
+        let lp_token_mint = accounts.key(10)?;
+
+        match self {
+            Self::AddLiquidity2(params) => Some(LiquidityChange {
+                kind: Kind::Deposit as i32,
+                pool,
+                input_tokens: vec![token],
+                input_token_amounts: vec![params.token_amount_in.to_string()],
+                output_token: lp_token_mint,
+                output_token_amount: accounts.balance(2).map_or(0, |c| c.delta()).to_string(),
+                owner,
+            }),
+            Self::RemoveLiquidity2(params) => Some(LiquidityChange {
+                kind: Kind::Withdraw as i32,
+                pool,
+                input_tokens: vec![token],
+                input_token_amounts: vec![accounts.balance(1).map_or(0, |c| c.delta()).to_string()],
+                output_token: lp_token_mint,
+                output_token_amount: params.lp_amount_in.to_string(),
+                owner,
+            }),
+            _ => None,
+        }
+    }
+}
+
+// Change and type of the position request an increase or decrease executes
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RequestChange {
+    None,
+    Increase,
+    Decrease,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, Copy, PartialEq)]
+pub enum RequestType {
+    Market,
+    Trigger,
+}
+
+// Position events, in the field order of the IDL. USD amounts have 6
+// decimals.
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct IncreasePositionEvent {
+    pub position_key: [u8; 32],
+    pub position_side: u8,
+    pub position_custody: [u8; 32],
+    pub position_collateral_custody: [u8; 32],
+    pub position_size_usd: u64,
+    pub position_mint: [u8; 32],
+    pub position_request_key: [u8; 32],
+    pub position_request_mint: [u8; 32],
+    pub position_request_change: RequestChange,
+    pub position_request_type: RequestType,
+    pub position_request_collateral_delta: u64,
+    pub owner: [u8; 32],
+    pub pool: [u8; 32],
+    pub size_usd_delta: u64,
+    pub collateral_usd_delta: u64,
+    pub collateral_token_delta: u64,
+    pub price: u64,
+    pub price_slippage: Option<u64>,
+    pub fee_token: u64,
+    pub fee_usd: u64,
+    pub open_time: i64,
+    pub referral: Option<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct DecreasePositionEvent {
+    pub position_key: [u8; 32],
+    pub position_side: u8,
+    pub position_custody: [u8; 32],
+    pub position_collateral_custody: [u8; 32],
+    pub position_size_usd: u64,
+    pub position_mint: [u8; 32],
+    pub position_request_key: [u8; 32],
+    pub position_request_mint: [u8; 32],
+    pub position_request_change: RequestChange,
+    pub position_request_type: RequestType,
+    pub position_request_collateral_delta: u64,
+    pub owner: [u8; 32],
+    pub pool: [u8; 32],
+    pub size_usd_delta: u64,
+    pub collateral_usd_delta: u64,
+    pub has_profit: bool,
+    pub pnl_delta: u64,
+    pub transfer_amount_usd: u64,
+    pub transfer_token: Option<u64>,
+    pub price: u64,
+    pub price_slippage: Option<u64>,
+    pub fee_usd: u64,
+    pub open_time: i64,
+    pub referral: Option<[u8; 32]>,
+}
+
+// Emitted by instantIncreasePosition and instantDecreasePosition, which
+// execute without a position request
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InstantIncreasePositionEvent {
+    pub position_key: [u8; 32],
+    pub position_side: u8,
+    pub position_custody: [u8; 32],
+    pub position_collateral_custody: [u8; 32],
+    pub position_size_usd: u64,
+    pub position_mint: [u8; 32],
+    pub owner: [u8; 32],
+    pub pool: [u8; 32],
+    pub size_usd_delta: u64,
+    pub collateral_usd_delta: u64,
+    pub collateral_token_delta: u64,
+    pub price: u64,
+    pub price_slippage: u64,
+    pub fee_token: u64,
+    pub fee_usd: u64,
+    pub open_time: i64,
+    pub referral: Option<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct InstantDecreasePositionEvent {
+    pub position_key: [u8; 32],
+    pub position_side: u8,
+    pub position_custody: [u8; 32],
+    pub position_collateral_custody: [u8; 32],
+    pub position_size_usd: u64,
+    pub position_mint: [u8; 32],
+    pub desired_mint: [u8; 32],
+    pub owner: [u8; 32],
+    pub pool: [u8; 32],
+    pub has_profit: bool,
+    pub pnl_delta: u64,
+    pub transfer_amount_usd: u64,
+    pub transfer_token: u64,
+    pub size_usd_delta: u64,
+    pub collateral_usd_delta: u64,
+    pub price: u64,
+    pub price_slippage: u64,
+    pub fee_usd: u64,
+    pub open_time: i64,
+    pub referral: Option<[u8; 32]>,
+}
+
+#[derive(BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct LiquidateFullPositionEvent {
+    pub position_key: [u8; 32],
+    pub position_side: u8,
+    pub position_custody: [u8; 32],
+    pub position_collateral_custody: [u8; 32],
+    pub position_collateral_mint: [u8; 32],
+    pub position_mint: [u8; 32],
+    pub position_size_usd: u64,
+    pub has_profit: bool,
+    pub pnl_delta: u64,
+    pub transfer_amount_token: u64,
+    pub price: u64,
+    pub fee_usd: u64,
+    pub liquidation_fee_usd: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Increase(IncreasePositionEvent),
+    Decrease(DecreasePositionEvent),
+    InstantIncrease(InstantIncreasePositionEvent),
+    InstantDecrease(InstantDecreasePositionEvent),
+    Liquidation(LiquidateFullPositionEvent),
+}
+
+impl Event {
+    // Decodes a position event from event instruction data, returns None for
+    // other events
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if let Some(mut args) = event_args(data, &INCREASE_POSITION_EVENT) {
+            IncreasePositionEvent::deserialize(&mut args).ok().map(Self::Increase)
+        } else if let Some(mut args) = event_args(data, &DECREASE_POSITION_EVENT) {
+            DecreasePositionEvent::deserialize(&mut args).ok().map(Self::Decrease)
+        } else if let Some(mut args) = event_args(data, &INSTANT_INCREASE_POSITION_EVENT) {
+            InstantIncreasePositionEvent::deserialize(&mut args).ok().map(Self::InstantIncrease)
+        } else if let Some(mut args) = event_args(data, &INSTANT_DECREASE_POSITION_EVENT) {
+            InstantDecreasePositionEvent::deserialize(&mut args).ok().map(Self::InstantDecrease)
+        } else if let Some(mut args) = event_args(data, &LIQUIDATE_FULL_POSITION_EVENT) {
+            LiquidateFullPositionEvent::deserialize(&mut args).ok().map(Self::Liquidation)
+        } else {
+            None
+        }
+    }
+}
+
+// LONG or SHORT, as the side is named in the schema
+pub fn side_name(side: u8) -> &'static str {
+    match side {
+        SIDE_LONG => "LONG",
+        SIDE_SHORT => "SHORT",
+        _ => "NONE",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{event_data, instruction_data};
+
+    // Increase of a long SOL position through a market request, laid out
+    // field by field in the order of the IDL
+    #[test]
+    fn decodes_increase_position_event() {
+        let data = event_data(&INCREASE_POSITION_EVENT, &[
+            &[1; 32],                              // positionKey
+            &[SIDE_LONG],                          // positionSide
+            &[2; 32],                              // positionCustody
+            &[3; 32],                              // positionCollateralCustody
+            &150_000_000u64.to_le_bytes(),         // positionSizeUsd
+            &[4; 32],                              // positionMint
+            &[5; 32],                              // positionRequestKey
+            &[6; 32],                              // positionRequestMint
+            &[1],                                  // positionRequestChange: Increase
+            &[0],                                  // positionRequestType: Market
+            &1_000_000_000u64.to_le_bytes(),       // positionRequestCollateralDelta
+            &[7; 32],                              // owner
+            &[8; 32],                              // pool
+            &100_000_000u64.to_le_bytes(),         // sizeUsdDelta
+            &20_000_000u64.to_le_bytes(),          // collateralUsdDelta
+            &1_000_000_000u64.to_le_bytes(),       // collateralTokenDelta
+            &145_250_000u64.to_le_bytes(),         // price
+            &[1], &145_500_000u64.to_le_bytes(),   // priceSlippage: Some
+            &400_000u64.to_le_bytes(),             // feeToken
+            &60_000u64.to_le_bytes(),              // feeUsd
+            &1_700_000_000i64.to_le_bytes(),       // openTime
+            &[0],                                  // referral: None
+        ]);
+
+        let Some(Event::Increase(event)) = Event::decode(&data) else {
+            panic!("not an increase event");
+        };
+        assert_eq!(event.position_key, [1; 32]);
+        assert_eq!(event.position_side, SIDE_LONG);
+        assert_eq!(event.position_size_usd, 150_000_000);
+        assert_eq!(event.position_request_change, RequestChange::Increase);
+        assert_eq!(event.position_request_type, RequestType::Market);
+        assert_eq!(event.position_request_collateral_delta, 1_000_000_000);
+        assert_eq!(event.owner, [7; 32]);
+        assert_eq!(event.pool, [8; 32]);
+        assert_eq!(event.size_usd_delta, 100_000_000);
+        assert_eq!(event.collateral_usd_delta, 20_000_000);
+        assert_eq!(event.price, 145_250_000);
+        assert_eq!(event.price_slippage, Some(145_500_000));
+        assert_eq!(event.fee_usd, 60_000);
+        assert_eq!(event.open_time, 1_700_000_000);
+        assert_eq!(event.referral, None);
+    }
+
+    #[test]
+    fn decodes_decrease_position_event() {
+        let data = event_data(&DECREASE_POSITION_EVENT, &[
+            &[1; 32],                              // positionKey
+            &[SIDE_SHORT],                         // positionSide
+            &[2; 32],                              // positionCustody
+            &[3; 32],                              // positionCollateralCustody
+            &0u64.to_le_bytes(),                   // positionSizeUsd
+            &[4; 32],                              // positionMint
+            &[5; 32],                              // positionRequestKey
+            &[6; 32],                              // positionRequestMint
+            &[2],                                  // positionRequestChange: Decrease
+            &[1],                                  // positionRequestType: Trigger
+            &0u64.to_le_bytes(),                   // positionRequestCollateralDelta
+            &[7; 32],                              // owner
+            &[8; 32],                              // pool
+            &100_000_000u64.to_le_bytes(),         // sizeUsdDelta
+            &20_000_000u64.to_le_bytes(),          // collateralUsdDelta
+            &[1],                                  // hasProfit
+            &3_500_000u64.to_le_bytes(),           // pnlDelta
+            &23_440_000u64.to_le_bytes(),          // transferAmountUsd
+            &[1], &23_440_000u64.to_le_bytes(),    // transferToken: Some
+            &140_000_000u64.to_le_bytes(),         // price
+            &[0],                                  // priceSlippage: None
+            &60_000u64.to_le_bytes(),              // feeUsd
+            &1_700_000_000i64.to_le_bytes(),       // openTime
+            &[1], &[9; 32],                        // referral: Some
+        ]);
+
+        let Some(Event::Decrease(event)) = Event::decode(&data) else {
+            panic!("not a decrease event");
+        };
+        assert_eq!(event.position_side, SIDE_SHORT);
+        assert_eq!(event.position_request_change, RequestChange::Decrease);
+        assert_eq!(event.position_request_type, RequestType::Trigger);
+        assert_eq!(event.owner, [7; 32]);
+        assert_eq!(event.size_usd_delta, 100_000_000);
+        assert_eq!(event.collateral_usd_delta, 20_000_000);
+        assert!(event.has_profit);
+        assert_eq!(event.pnl_delta, 3_500_000);
+        assert_eq!(event.transfer_token, Some(23_440_000));
+        assert_eq!(event.price, 140_000_000);
+        assert_eq!(event.price_slippage, None);
+        assert_eq!(event.referral, Some([9; 32]));
+    }
+
+    #[test]
+    fn decodes_liquidity_instructions() {
+        let data = instruction_data(&ADD_LIQUIDITY2, &[&1_000u64.to_le_bytes(), &900u64.to_le_bytes(), &[0]]);
+        assert_eq!(PerpsInstruction::decode(&data), Some(PerpsInstruction::AddLiquidity2(AddLiquidity2Params {
+            token_amount_in: 1_000,
+            min_lp_amount_out: 900,
+            token_amount_pre_swap: None,
+        })));
+
+        let data = instruction_data(&REMOVE_LIQUIDITY2, &[&900u64.to_le_bytes(), &950u64.to_le_bytes()]);
+        assert_eq!(PerpsInstruction::decode(&data), Some(PerpsInstruction::RemoveLiquidity2(RemoveLiquidity2Params {
+            lp_amount_in: 900,
+            min_amount_out: 950,
+        })));
+    }
+
+    #[test]
+    fn decodes_position_instructions() {
+        let instructions = [
+            (CREATE_INCREASE_POSITION_MARKET_REQUEST, PerpsInstruction::CreateIncreasePositionMarketRequest),
+            (CREATE_DECREASE_POSITION_MARKET_REQUEST, PerpsInstruction::CreateDecreasePositionMarketRequest),
+            (INCREASE_POSITION4, PerpsInstruction::IncreasePosition4),
+            (DECREASE_POSITION4, PerpsInstruction::DecreasePosition4),
+            (INSTANT_INCREASE_POSITION, PerpsInstruction::InstantIncreasePosition),
+            (INSTANT_DECREASE_POSITION, PerpsInstruction::InstantDecreasePosition),
+            (LIQUIDATE_FULL_POSITION4, PerpsInstruction::LiquidateFullPosition4),
+        ];
+        for (discriminator, instruction) in instructions {
+            // Args are not decoded, so they do not affect the result
+            let data = instruction_data(&discriminator, &[&[0; 24]]);
+            assert_eq!(PerpsInstruction::decode(&data), Some(instruction));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_and_malformed_instructions() {
+        assert_eq!(PerpsInstruction::decode(&[0; 16]), None);
+        assert_eq!(PerpsInstruction::decode(&ADD_LIQUIDITY2[..4]), None);
+        assert_eq!(PerpsInstruction::decode(&instruction_data(&REMOVE_LIQUIDITY2, &[&900u64.to_le_bytes()])), None);
+    }
+
+    #[test]
+    fn decodes_liquidate_full_position_event() {
+        let data = event_data(&LIQUIDATE_FULL_POSITION_EVENT, &[
+            &[1; 32],                      // positionKey
+            &[SIDE_SHORT],                 // positionSide
+            &[2; 32],                      // positionCustody
+            &[3; 32],                      // positionCollateralCustody
+            &[4; 32],                      // positionCollateralMint
+            &[5; 32],                      // positionMint
+            &150_000_000u64.to_le_bytes(), // positionSizeUsd
+            &[0],                          // hasProfit
+            &19_000_000u64.to_le_bytes(),  // pnlDelta
+            &5_000u64.to_le_bytes(),       // transferAmountToken
+            &160_000_000u64.to_le_bytes(), // price
+            &90_000u64.to_le_bytes(),      // feeUsd
+            &750_000u64.to_le_bytes(),     // liquidationFeeUsd
+        ]);
+        assert_eq!(Event::decode(&data), Some(Event::Liquidation(LiquidateFullPositionEvent {
+            position_key: [1; 32],
+            position_side: SIDE_SHORT,
+            position_custody: [2; 32],
+            position_collateral_custody: [3; 32],
+            position_collateral_mint: [4; 32],
+            position_mint: [5; 32],
+            position_size_usd: 150_000_000,
+            has_profit: false,
+            pnl_delta: 19_000_000,
+            transfer_amount_token: 5_000,
+            price: 160_000_000,
+            fee_usd: 90_000,
+            liquidation_fee_usd: 750_000,
+        })));
+    }
+
+    #[test]
+    fn decodes_instant_increase_position_event() {
+        let data = event_data(&INSTANT_INCREASE_POSITION_EVENT, &[
+            &[1; 32],                              // positionKey
+            &[SIDE_LONG],                          // positionSide
+            &[2; 32],                              // positionCustody
+            &[3; 32],                              // positionCollateralCustody
+            &100_000_000u64.to_le_bytes(),         // positionSizeUsd
+            &[4; 32],                              // positionMint
+            &[7; 32],                              // owner
+            &[8; 32],                              // pool
+            &100_000_000u64.to_le_bytes(),         // sizeUsdDelta
+            &20_000_000u64.to_le_bytes(),          // collateralUsdDelta
+            &1_000_000_000u64.to_le_bytes(),       // collateralTokenDelta
+            &145_250_000u64.to_le_bytes(),         // price
+            &145_500_000u64.to_le_bytes(),         // priceSlippage
+            &400_000u64.to_le_bytes(),             // feeToken
+            &60_000u64.to_le_bytes(),              // feeUsd
+            &1_700_000_000i64.to_le_bytes(),       // openTime
+            &[0],                                  // referral: None
+        ]);
+
+        let Some(Event::InstantIncrease(event)) = Event::decode(&data) else {
+            panic!("not an instant increase event");
+        };
+        assert_eq!(event.position_key, [1; 32]);
+        assert_eq!(event.owner, [7; 32]);
+        assert_eq!(event.size_usd_delta, 100_000_000);
+        assert_eq!(event.collateral_usd_delta, 20_000_000);
+        assert_eq!(event.price, 145_250_000);
+        assert_eq!(event.fee_usd, 60_000);
+        assert_eq!(event.referral, None);
+    }
+
+    #[test]
+    fn decodes_instant_decrease_position_event() {
+        let data = event_data(&INSTANT_DECREASE_POSITION_EVENT, &[
+            &[1; 32],                              // positionKey
+            &[SIDE_SHORT],                         // positionSide
+            &[2; 32],                              // positionCustody
+            &[3; 32],                              // positionCollateralCustody
+            &0u64.to_le_bytes(),                   // positionSizeUsd
+            &[4; 32],                              // positionMint
+            &[5; 32],                              // desiredMint
+            &[7; 32],                              // owner
+            &[8; 32],                              // pool
+            &[0],                                  // hasProfit
+            &2_000_000u64.to_le_bytes(),           // pnlDelta
+            &17_940_000u64.to_le_bytes(),          // transferAmountUsd
+            &17_940_000u64.to_le_bytes(),          // transferToken
+            &100_000_000u64.to_le_bytes(),         // sizeUsdDelta
+            &20_000_000u64.to_le_bytes(),          // collateralUsdDelta
+            &150_000_000u64.to_le_bytes(),         // price
+            &149_800_000u64.to_le_bytes(),         // priceSlippage
+            &60_000u64.to_le_bytes(),              // feeUsd
+            &1_700_000_000i64.to_le_bytes(),       // openTime
+            &[1], &[9; 32],                        // referral: Some
+        ]);
+
+        let Some(Event::InstantDecrease(event)) = Event::decode(&data) else {
+            panic!("not an instant decrease event");
+        };
+        assert_eq!(event.position_side, SIDE_SHORT);
+        assert_eq!(event.desired_mint, [5; 32]);
+        assert_eq!(event.owner, [7; 32]);
+        assert!(!event.has_profit);
+        assert_eq!(event.pnl_delta, 2_000_000);
+        assert_eq!(event.size_usd_delta, 100_000_000);
+        assert_eq!(event.collateral_usd_delta, 20_000_000);
+        assert_eq!(event.price, 150_000_000);
+        assert_eq!(event.referral, Some([9; 32]));
+    }
+
+    #[test]
+    fn rejects_truncated_events() {
+        let data = event_data(&INCREASE_POSITION_EVENT, &[&[1; 32], &[SIDE_LONG]]);
+        assert_eq!(Event::decode(&data), None);
+    }
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+
+use substreams::errors::Error;
+
+// Vetted Jupiter deployments, used unless the `programs` param is set
+const DEFAULT_PROGRAMS: &[(&str, ProgramKind)] = &[
+    ("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4", ProgramKind::AggregatorV6),
+    ("JUP4Fb2cqiRUcaTHdrPC8h2g7yFhLMZB19XWN5Q7McL", ProgramKind::AggregatorV4),
+    ("jupoNjAxXgZ4rjzxzPMP4oxduvQsQtZzyknqvzYNrNu", ProgramKind::LimitOrder),
+    ("DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M", ProgramKind::Dca),
+    ("PERPHjGBqRHArX4DySjwM6UJHiR3sWAatqfdBS2qQJu", ProgramKind::Perps),
+];
+
+// Jupiter product implemented by a program, which selects its decoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramKind {
+    AggregatorV6,
+    AggregatorV4,
+    LimitOrder,
+    Dca,
+    Perps,
+}
+
+impl ProgramKind {
+    // Parses the kind of a `programs` param entry
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "v6" => Some(Self::AggregatorV6),
+            "v4" => Some(Self::AggregatorV4),
+            "limit_order" => Some(Self::LimitOrder),
+            "dca" => Some(Self::Dca),
+            "perps" => Some(Self::Perps),
+            _ => None,
+        }
+    }
+
+    // Label used when the `programs` param does not set one
+    pub fn default_label(&self) -> &'static str {
+        match self {
+            Self::AggregatorV6 => "v6",
+            Self::AggregatorV4 => "v4",
+            Self::LimitOrder => "Limit Order",
+            Self::Dca => "DCA",
+            Self::Perps => "Perps",
+        }
+    }
+
+    // DCA, Limit Order and Perps instructions move tokens in and out of
+    // positions rather than swapping them
+    pub fn is_product(&self) -> bool {
+        matches!(self, Self::LimitOrder | Self::Dca | Self::Perps)
+    }
+}
+
+// A Jupiter program, its kind and its display label
+#[derive(Debug, Clone, PartialEq)]
+pub struct JupiterProgram {
+    pub id: String,
+    pub kind: ProgramKind,
+    pub label: String,
+}
+
+// Programs keyed by their raw pubkey, so instructions and accounts are
+// matched without base58-encoding them first
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgramSet {
+    programs: BTreeMap<[u8; 32], JupiterProgram>,
+}
+
+impl Default for ProgramSet {
+    fn default() -> Self {
+        let mut programs = BTreeMap::new();
+        for &(id, kind) in DEFAULT_PROGRAMS {
+            let key = decode_pubkey(id).expect("invalid default program id");
+            programs.insert(key, JupiterProgram { id: id.to_string(), kind, label: kind.default_label().to_string() });
+        }
+
+        ProgramSet { programs }
+    }
+}
+
+impl ProgramSet {
+    // Parses a comma separated list of `<program id>:<kind>[:<label>]`
+    // entries, the kind being one of v6, v4, limit_order, dca or perps
+    pub fn parse(value: &str) -> Result<Self, Error> {
+        let mut programs = BTreeMap::new();
+
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (id, kind_and_label) = entry.split_once(':')
+                .ok_or_else(|| Error::msg(format!("invalid program `{}`, expected <program id>:<kind>[:<label>]", entry)))?;
+            let (kind, label) = match kind_and_label.split_once(':') {
+                Some((kind, label)) => (kind.trim(), Some(label.trim())),
+                None => (kind_and_label.trim(), None),
+            };
+            let id = id.trim();
+            let key = decode_pubkey(id)
+                .ok_or_else(|| Error::msg(format!("invalid program id `{}`", id)))?;
+            let kind = ProgramKind::parse(kind)
+                .ok_or_else(|| Error::msg(format!("invalid program kind `{}`, expected v6, v4, limit_order, dca or perps", kind)))?;
+
+            programs.insert(key, JupiterProgram {
+                id: id.to_string(),
+                kind,
+                label: label.unwrap_or(kind.default_label()).to_string(),
+            });
+        }
+
+        // An empty set would silently index nothing
+        if programs.is_empty() {
+            return Err(Error::msg("empty `programs` param, expected at least one <program id>:<kind>[:<label>]"));
+        }
+
+        Ok(ProgramSet { programs })
+    }
+
+    // Looks up a raw pubkey, slices that are not 32 bytes long never match
+    pub fn get(&self, key: &[u8]) -> Option<&JupiterProgram> {
+        let key: &[u8; 32] = key.try_into().ok()?;
+        self.programs.get(key)
+    }
+
+    pub fn contains(&self, key: &[u8]) -> bool {
+        self.get(key).is_some()
+    }
+
+    pub fn kind(&self, key: &[u8]) -> Option<ProgramKind> {
+        self.get(key).map(|p| p.kind)
+    }
+
+    // Display label of the program, "Unknown" for programs outside the set
+    pub fn label(&self, key: &[u8]) -> &str {
+        self.get(key).map_or("Unknown", |p| p.label.as_str())
+    }
+}
+
+// Decodes a base58 pubkey, None if it is not 32 bytes long
+pub fn decode_pubkey(value: &str) -> Option<[u8; 32]> {
+    bs58::decode(value).into_vec().ok()?.try_into().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_programs_param() {
+        let programs = ProgramSet::parse("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4:v6, DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M:dca:Recurring").unwrap();
+        let v6 = decode_pubkey("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4").unwrap();
+        let dca = decode_pubkey("DCA265Vj8a9CEuX1eb1LWRnDT7uK6q1xMipnNyatn23M").unwrap();
+        assert_eq!(programs.kind(&v6), Some(ProgramKind::AggregatorV6));
+        assert_eq!(programs.label(&v6), "v6");
+        assert_eq!(programs.label(&dca), "Recurring");
+        assert!(!programs.contains(&decode_pubkey("jupoNjAxXgZ4rjzxzPMP4oxduvQsQtZzyknqvzYNrNu").unwrap()));
+    }
+
+    #[test]
+    fn rejects_empty_and_invalid_programs() {
+        assert!(ProgramSet::parse("").is_err());
+        assert!(ProgramSet::parse(" , ").is_err());
+        assert!(ProgramSet::parse("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4").is_err());
+        assert!(ProgramSet::parse("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4:v5").is_err());
+        assert!(ProgramSet::parse("JUP6Lk:v6").is_err());
+    }
+}